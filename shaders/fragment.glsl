@@ -1,11 +1,21 @@
 #version 100
 varying highp vec2 v_tex_coord;
 varying mediump vec4 v_color;
+varying mediump float v_additive;
 
 uniform highp vec2 texture_size;
 uniform sampler2D texture;
+uniform highp vec2 clip_min;
+uniform highp vec2 clip_max;
 
 void main()
 {
-    gl_FragColor = texture2D(texture, v_tex_coord / texture_size) * v_color;
+    if (any(lessThan(gl_FragCoord.xy, clip_min)) || any(greaterThan(gl_FragCoord.xy, clip_max))) {
+        discard;
+    }
+
+    mediump vec4 color = texture2D(texture, v_tex_coord / texture_size) * v_color;
+    // premultiplied output for (ONE, ONE_MINUS_SRC_ALPHA) blending, additive colors have zero
+    // alpha so they don't darken what is behind them
+    gl_FragColor = vec4(color.rgb * color.a, color.a * (1.0 - v_additive));
 }
@@ -0,0 +1,40 @@
+#version 100
+// Draws shapes from their signed distance field, everything is a rounded rect:
+// a circle has a corner radius equal to its half size and a capsule is a rect with fully rounded
+// ends.
+varying highp vec2 v_local_position;
+// half width, half height, corner radius, outline width (0 for filled shapes)
+varying highp vec4 v_shape;
+varying mediump vec4 v_color;
+varying mediump float v_additive;
+
+uniform highp vec2 view_scale;
+uniform highp vec2 clip_min;
+uniform highp vec2 clip_max;
+
+highp float rounded_rect_distance(highp vec2 p, highp vec2 half_size, highp float radius)
+{
+    highp vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main()
+{
+    if (any(lessThan(gl_FragCoord.xy, clip_min)) || any(greaterThan(gl_FragCoord.xy, clip_max))) {
+        discard;
+    }
+
+    highp float d = rounded_rect_distance(v_local_position, v_shape.xy, v_shape.z);
+    if (v_shape.w > 0.0) {
+        // keep the outline inside the shape's edge
+        d = abs(d + v_shape.w * 0.5) - v_shape.w * 0.5;
+    }
+
+    // one pixel wide anti-aliased edge
+    highp float pixel = 1.0 / view_scale.x;
+    highp float coverage = clamp(0.5 - d / pixel, 0.0, 1.0);
+
+    mediump float alpha = v_color.a * coverage;
+    // premultiplied output, see fragment.glsl
+    gl_FragColor = vec4(v_color.rgb * alpha, alpha * (1.0 - v_additive));
+}
@@ -0,0 +1,25 @@
+#version 100
+attribute highp vec2 position;
+attribute highp vec2 local_position;
+attribute highp vec4 shape;
+attribute mediump vec4 color;
+attribute mediump float additive;
+
+uniform vec2 screen_size;
+uniform vec2 view_scale;
+uniform vec2 view_offset;
+
+varying highp vec2 v_local_position;
+varying highp vec4 v_shape;
+varying mediump vec4 v_color;
+varying mediump float v_additive;
+
+void main()
+{
+    vec2 screen_position = position * view_scale + view_offset;
+    gl_Position = vec4((screen_position / screen_size * 2.0) - vec2(1.0, 1.0), 0.0, 1.0);
+    v_local_position = local_position;
+    v_shape = shape;
+    v_color = color;
+    v_additive = additive;
+}
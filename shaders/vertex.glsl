@@ -2,15 +2,21 @@
 attribute highp vec2 position;
 attribute highp vec2 tex_coord;
 attribute mediump vec4 color;
+attribute mediump float additive;
 
 uniform vec2 screen_size;
+uniform vec2 view_scale;
+uniform vec2 view_offset;
 
 varying vec2 v_tex_coord;
 varying mediump vec4 v_color;
+varying mediump float v_additive;
 
 void main()
 {
-    gl_Position = vec4((position / screen_size * 2.0) - vec2(1.0, 1.0), 0.0, 1.0);
+    vec2 screen_position = position * view_scale + view_offset;
+    gl_Position = vec4((screen_position / screen_size * 2.0) - vec2(1.0, 1.0), 0.0, 1.0);
     v_color = color;
     v_tex_coord = tex_coord;
+    v_additive = additive;
 }
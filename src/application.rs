@@ -1,31 +1,210 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
 use failure::Error;
 
 use embla;
-use embla::input::Input;
+use embla::input::{Input, Key};
 
+use controls::Controls;
 use game::Game;
+use grid::Grid;
+use level::Level;
+use recording_renderer::RecordingRenderer;
+use render_interface::RenderInterface;
+use replay::{Replay, ReplayRecorder};
 use renderer::GameRenderer;
+use software_renderer::SoftwareRenderer;
+use ui::{Ui, UiStyle};
 
 pub struct Application {
     renderer: GameRenderer<embla::Renderer>,
     client: Game,
+    recorder: Option<ReplayRecorder>,
+    ui: Ui,
+    settings_open: bool,
+    // restarting plays the same level again
+    level: Level,
+    // name of the replay started from the settings panel
+    replay_name: String,
 }
 
+// frame rate of captured image sequences
+const CAPTURE_FPS: f32 = 30.0;
+
 impl Application {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(recorder: Option<ReplayRecorder>, level: Level) -> Result<Self, Error> {
         Ok(Application {
             renderer: GameRenderer::<embla::Renderer>::new()?,
-            client: Game::new()?,
+            client: Game::new(&level)?,
+            level,
+            recorder,
+            ui: Ui::new(UiStyle::default()),
+            settings_open: false,
+            replay_name: String::new(),
         })
     }
 
     pub fn update(&mut self, dt: f32, input: &Input) -> Result<(), Error> {
-        self.client.update(dt, input)?;
+        let screen_size = self.renderer.screen_size();
+        self.client.set_screen_size(screen_size);
+        let mut controls = Controls::from_input(input);
+        if input.key_is_pressed(&Key::Escape) {
+            self.settings_open = !self.settings_open;
+        }
+        if self.settings_open {
+            // keys typed into the panel are not game commands
+            if self.ui.wants_keyboard() {
+                controls.commands.clear();
+                controls.down.clear();
+            }
+            self.client.settings_panel(input, &mut self.ui, &mut controls);
+            self.replay_panel()?;
+            self.ui.end();
+            if self.ui.wants_mouse() {
+                controls.place_wall = false;
+                controls.pan_view = false;
+            }
+        }
+
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(dt, screen_size, &controls)?;
+        }
+        self.client.update(dt, &controls)?;
 
         self.client.render(&mut self.renderer)?;
+        if self.settings_open {
+            self.ui.render(&mut self.renderer)?;
+        }
 
         self.renderer.do_render().unwrap();
 
         Ok(())
     }
 }
+
+impl Application {
+    /// Records to `<name>.replay`, restarting the game so the replay plays back from the start.
+    fn replay_panel(&mut self) -> Result<(), Error> {
+        let recording = self.recorder.is_some();
+        let name = &mut self.replay_name;
+        let mut record = false;
+        let mut stop = false;
+        self.ui.row(|ui| {
+            ui.text_field("REPLAY NAME", name);
+            if recording {
+                stop = ui.button("STOP");
+            } else {
+                record = ui.button("RECORD") && !name.is_empty();
+            }
+        });
+
+        if record {
+            let path = format!("{}.replay", self.replay_name.to_lowercase());
+            self.recorder = Some(ReplayRecorder::create(path)?);
+            self.client = Game::new(&self.level)?;
+        }
+        if stop {
+            self.recorder = None;
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders the game's first frame without a window and saves it as a PNG, for comparing against
+/// known good images.
+pub fn render_snapshot(path: &str, size: (i32, i32), level: &Level) -> Result<(), Error> {
+    let mut game = Game::new(level)?;
+    let mut renderer = SoftwareRenderer::new(size);
+
+    game.render(&mut renderer)?;
+    renderer.do_render();
+
+    renderer.save_png(path)
+}
+
+/// Plays back a replay without a window and saves it as a PNG sequence at a fixed frame rate,
+/// named `frame_00000.png` onwards in `out_dir`. Frames are the size of the recorded window, or
+/// `size` for replays that don't say.
+pub fn capture<P: AsRef<Path>>(
+    replay: &str,
+    out_dir: P,
+    size: (i32, i32),
+    level: &Level,
+) -> Result<(), Error> {
+    let replay = Replay::load(replay)?;
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+
+    let size = replay.screen_size().unwrap_or(size);
+    let mut game = Game::new(level)?;
+    let mut renderer = SoftwareRenderer::new(size);
+    let mut time = 0.0;
+    let mut frame = 0;
+    for replay_frame in replay.frames {
+        // mouse positions only make sense in the window they were recorded in
+        game.set_screen_size(replay_frame.screen_size.unwrap_or(size));
+        game.update(replay_frame.dt, &replay_frame.controls)?;
+        time += replay_frame.dt;
+
+        // replays are recorded at whatever rate the game ran, so skip or repeat frames to match
+        while time >= frame as f32 / CAPTURE_FPS {
+            game.render(&mut renderer)?;
+            renderer.do_render();
+            renderer.save_png(out_dir.join(format!("frame_{:05}.png", frame)))?;
+            frame += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints every draw call of the game's first frame.
+pub fn dump_draws(size: (i32, i32), level: &Level) -> Result<(), Error> {
+    let mut game = Game::new(level)?;
+    let mut renderer = RecordingRenderer::new(size);
+
+    game.render(&mut renderer)?;
+    for draw in renderer.draws() {
+        println!("{:?}", draw);
+    }
+
+    Ok(())
+}
+
+/// Times finding paths for `blobs` blobs on a `size` by `size` grid with A* for every blob against
+/// a single shared flow field.
+pub fn bench_pathfinding(size: i32, blobs: usize) {
+    // a fixed pseudo random sequence so runs are comparable
+    let mut seed: u32 = 12345;
+    let mut random = move |max: i32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        ((seed >> 16) % max as u32) as i32
+    };
+
+    let goal = (size / 2, 0);
+    let mut grid = Grid::new(size, size, 1, 1);
+    for _ in 0..size * size / 5 {
+        let tile = (random(size), random(size - 1) + 1);
+        grid.set_occupied(tile, true);
+    }
+    let starts = (0..blobs)
+        .map(|_| (random(size), size - 1))
+        .collect::<Vec<_>>();
+
+    let time = Instant::now();
+    let found = starts
+        .iter()
+        .filter_map(|&start| grid.find_path(start, goal))
+        .count();
+    println!("a*: {} paths in {:?}", found, time.elapsed());
+
+    let time = Instant::now();
+    let found = starts
+        .iter()
+        .filter_map(|&start| grid.flow_path(start, goal))
+        .count();
+    println!("flow field: {} paths in {:?}", found, time.elapsed());
+}
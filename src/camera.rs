@@ -0,0 +1,113 @@
+use embla::math::Vec2;
+
+/// Maps world coordinates to the screen.
+///
+/// The camera fits a fixed-size view of the world into the window while preserving its aspect
+/// ratio, so the board scales with the window and any leftover space becomes letterbox bars.
+/// Zoom and pan are applied on top of the fitted view.
+pub struct Camera {
+    view_size: Vec2,
+    screen_size: Vec2,
+    center: Vec2,
+    zoom: f32,
+}
+
+impl Camera {
+    pub fn new(view_size: Vec2) -> Camera {
+        Camera {
+            view_size,
+            screen_size: view_size,
+            center: view_size / 2.0,
+            zoom: 1.0,
+        }
+    }
+
+    pub fn set_screen_size(&mut self, screen_size: (i32, i32)) {
+        self.screen_size = Vec2::new(screen_size.0 as f32, screen_size.1 as f32);
+    }
+
+    pub fn center_on(&mut self, center: Vec2) {
+        self.center = center;
+    }
+
+    pub fn pan(&mut self, offset: Vec2) {
+        self.center = self.center + offset;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.1);
+    }
+
+    // scale that fits the whole view into the screen
+    fn fit_scale(&self) -> f32 {
+        let scale =
+            (self.screen_size.0 / self.view_size.0).min(self.screen_size.1 / self.view_size.1);
+        if scale > 0.0 {
+            scale
+        } else {
+            1.0
+        }
+    }
+
+    /// World units to screen pixels.
+    pub fn scale(&self) -> f32 {
+        self.fit_scale() * self.zoom
+    }
+
+    /// Screen position of the world origin, in pixels from the bottom left corner.
+    pub fn offset(&self) -> Vec2 {
+        self.screen_size / 2.0 - self.center * self.scale()
+    }
+
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        (pos - self.offset()) / self.scale()
+    }
+
+    /// Converts a mouse position (origin in the top left corner) to world coordinates.
+    pub fn mouse_to_world(&self, mouse_position: Vec2) -> Vec2 {
+        self.screen_to_world(Vec2::new(
+            mouse_position.0,
+            self.screen_size.1 - mouse_position.1,
+        ))
+    }
+
+    /// The letterboxed area of the screen that the view is fitted into, in pixels.
+    pub fn viewport(&self) -> (f32, f32, f32, f32) {
+        let size = self.view_size * self.fit_scale();
+        let min = (self.screen_size - size) / 2.0;
+        (min.0, min.1, min.0 + size.0, min.1 + size.1)
+    }
+
+    /// The viewport in world coordinates.
+    pub fn world_viewport(&self) -> (f32, f32, f32, f32) {
+        let (x0, y0, x1, y1) = self.viewport();
+        let min = self.screen_to_world(Vec2::new(x0, y0));
+        let max = self.screen_to_world(Vec2::new(x1, y1));
+        (min.0, min.1, max.0, max.1)
+    }
+
+    /// World space rects covering everything on screen outside of the viewport.
+    pub fn letterbox(&self) -> Vec<(f32, f32, f32, f32)> {
+        let (x0, y0, x1, y1) = self.viewport();
+        let (w, h) = (self.screen_size.0, self.screen_size.1);
+        let bars = [
+            (0.0, 0.0, x0, h),
+            (x1, 0.0, w, h),
+            (x0, 0.0, x1, y0),
+            (x0, y1, x1, h),
+        ];
+
+        bars.iter()
+            .filter(|b| b.2 > b.0 && b.3 > b.1)
+            .map(|b| {
+                let min = self.screen_to_world(Vec2::new(b.0, b.1));
+                let max = self.screen_to_world(Vec2::new(b.2, b.3));
+                (min.0, min.1, max.0, max.1)
+            })
+            .collect()
+    }
+}
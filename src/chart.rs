@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::Path;
+
+use failure::{err_msg, Error};
+
+use components::{Note, PadTeam};
+
+/// Loads a chart file, see `parse_chart`.
+pub fn load_chart<P: AsRef<Path>>(path: P) -> Result<Vec<Note>, Error> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    parse_chart(&source).map_err(|e| err_msg(format!("{}: {}", path.display(), e)))
+}
+
+/// Parses whitespace separated notes `<length>[+<team>...]`, with `#` starting a comment.
+pub fn parse_chart(source: &str) -> Result<Vec<Note>, Error> {
+    let mut notes = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        for token in line.split_whitespace() {
+            let note = parse_note(token)
+                .map_err(|e| err_msg(format!("line {}: `{}`: {}", i + 1, token, e)))?;
+            notes.push(note);
+        }
+    }
+
+    Ok(notes)
+}
+
+fn parse_note(token: &str) -> Result<Note, Error> {
+    let mut parts = token.split('+');
+    let length = parts.next().unwrap().parse::<i32>()?;
+    if length < 0 {
+        return Err(err_msg("expected a length of 0 or more"));
+    }
+
+    let teams = parts
+        .map(|name| {
+            PadTeam::from_name(name).ok_or_else(|| err_msg(format!("unknown team `{}`", name)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Note::chord(length, &teams))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_chart;
+    use components::{Note, PadTeam};
+
+    #[test]
+    fn notes_and_chords() {
+        let chart = parse_chart("1 3 # long\n0 1+green+yellow\n").unwrap();
+        assert_eq!(
+            chart,
+            vec![
+                Note::new(1),
+                Note::new(3),
+                Note::new(0),
+                Note::chord(1, &[PadTeam::Green, PadTeam::Yellow]),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_notes_are_rejected() {
+        assert!(parse_chart("x").is_err());
+        assert!(parse_chart("-1").is_err());
+        assert!(parse_chart("1+").is_err());
+        assert!(parse_chart("1++red").is_err());
+        assert!(parse_chart("1+purple").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationState {
+    Idle,
+    Moving,
+    Hit,
+    Dying,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationTiming {
+    /// Frames per second.
+    Fps(f32),
+    /// Length of the whole clip in beats, looping clips stay in sync with the beat.
+    Beats(f32),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnimationClip {
+    /// Frame indices into the sprite sheet.
+    pub frames: Vec<usize>,
+    pub timing: AnimationTiming,
+    pub looping: bool,
+}
+
+/// Switches the frame of the entity's `Sprite` based on its current state.
+#[derive(Clone)]
+pub struct Animation {
+    pub clips: Vec<(AnimationState, AnimationClip)>,
+    pub state: AnimationState,
+    /// Time spent in the current state in seconds.
+    pub elapsed: f32,
+    /// Time spent in the current state in beats.
+    pub elapsed_beats: f32,
+}
+
+impl Animation {
+    pub fn new(clips: Vec<(AnimationState, AnimationClip)>) -> Animation {
+        Animation {
+            clips,
+            state: AnimationState::Idle,
+            elapsed: 0.0,
+            elapsed_beats: 0.0,
+        }
+    }
+
+    pub fn set_state(&mut self, state: AnimationState) {
+        if self.state != state {
+            self.state = state;
+            self.elapsed = 0.0;
+            self.elapsed_beats = 0.0;
+        }
+    }
+
+    pub fn has_clip(&self, state: AnimationState) -> bool {
+        self.clips.iter().any(|&(s, _)| s == state)
+    }
+
+    pub fn clip(&self) -> Option<&AnimationClip> {
+        self.clips
+            .iter()
+            .find(|&&(s, _)| s == self.state)
+            .map(|(_, clip)| clip)
+    }
+}
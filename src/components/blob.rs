@@ -1,10 +1,59 @@
+use components::{PadTeam, TeamSet};
+
+/// A blob in a spawn's chart.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Note {
+    /// Tiles the blob spans, 0 skips the spawn.
+    pub length: i32,
+    /// Teams that have to hit the blob along with the spawn's own team.
+    pub chord: TeamSet,
+}
+
+impl Note {
+    pub fn new(length: i32) -> Note {
+        Note {
+            length,
+            chord: TeamSet::default(),
+        }
+    }
+
+    pub fn chord(length: i32, teams: &[PadTeam]) -> Note {
+        Note {
+            length,
+            chord: TeamSet::of(teams),
+        }
+    }
+}
+
 pub struct BlobSpawn {
     pub interval: i32,
     pub timer: i32,
+    /// Blobs spawned in turn, looping. An empty chart spawns single tile blobs.
+    pub chart: Vec<Note>,
+    pub chart_index: usize,
 }
 pub struct BlobGoal;
 
 pub struct Blob {
+    /// Order the blob was spawned in, older blobs go first when tiles are crowded.
+    pub spawn_index: u32,
+    /// Index of the tile the blob is on in `path`.
     pub path_index: usize,
+    /// Tiles walked so far followed by the way to the goal, which is looked up again every beat.
     pub path: Vec<(i32, i32)>,
+    /// Beats left before the blob moves again.
+    pub held: i32,
+    /// Whether the blob stayed in place on the last beat.
+    pub waited: bool,
+    /// Whether the blob was stopped by a full tile on the last beat.
+    pub bumped: bool,
+    /// Opacity from 0 to 1, blobs fade out while teleporting.
+    pub visibility: f32,
+    /// Tiles the blob spans along its path, long blobs have to be held on a pad for this many
+    /// beats.
+    pub length: i32,
+    /// Beats a long blob has been held on a pad, it stops moving once it is hit.
+    pub hold: Option<f32>,
+    /// Teams whose pads have to hit the blob, including the blob's own `PadTeam`.
+    pub teams: TeamSet,
 }
@@ -1,13 +1,21 @@
+mod animation;
 mod blob;
 mod pad;
 mod position;
 mod primitives;
+mod render_layer;
+mod sprite;
+mod team_gate;
 mod tile_position;
 mod tile_trigger;
 
+pub use self::animation::*;
 pub use self::blob::*;
 pub use self::pad::*;
 pub use self::position::*;
 pub use self::primitives::*;
+pub use self::render_layer::*;
+pub use self::sprite::*;
+pub use self::team_gate::*;
 pub use self::tile_position::*;
 pub use self::tile_trigger::*;
@@ -1,4 +1,8 @@
-#[derive(Clone, Copy, PartialEq)]
+use palette::Palette;
+use grid::Grid;
+use terrain::Direction;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum PadTeam {
     Blue,
     Red,
@@ -8,15 +12,198 @@ pub enum PadTeam {
 
 impl PadTeam {
     pub fn color(&self) -> (f32, f32, f32, f32) {
+        Palette::Default.team_color(*self)
+    }
+
+    /// Parses the lower case team name used in level and chart files.
+    pub fn from_name(name: &str) -> Option<PadTeam> {
+        match name {
+            "blue" => Some(PadTeam::Blue),
+            "red" => Some(PadTeam::Red),
+            "green" => Some(PadTeam::Green),
+            "yellow" => Some(PadTeam::Yellow),
+            _ => None,
+        }
+    }
+
+    fn bit(&self) -> u8 {
         match *self {
-            PadTeam::Blue => (0.5, 0.5, 1.0, 1.0),
-            PadTeam::Red => (1.0, 0.5, 0.5, 1.0),
-            PadTeam::Green => (0.5, 1.0, 0.5, 1.0),
-            PadTeam::Yellow => (1.0, 1.0, 0.5, 1.0),
+            PadTeam::Blue => 1,
+            PadTeam::Red => 2,
+            PadTeam::Green => 4,
+            PadTeam::Yellow => 8,
+        }
+    }
+}
+
+static TEAMS: [PadTeam; 4] = [PadTeam::Blue, PadTeam::Red, PadTeam::Green, PadTeam::Yellow];
+
+/// Teams a blob belongs to, chord blobs belong to several and need all their pads hit together.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct TeamSet(u8);
+
+impl TeamSet {
+    pub fn of(teams: &[PadTeam]) -> TeamSet {
+        let mut set = TeamSet::default();
+        for &team in teams {
+            set.insert(team);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, team: PadTeam) {
+        self.0 |= team.bit();
+    }
+
+    pub fn contains(&self, team: PadTeam) -> bool {
+        self.0 & team.bit() != 0
+    }
+
+    pub fn union(&self, other: TeamSet) -> TeamSet {
+        TeamSet(self.0 | other.0)
+    }
+
+    /// The teams in the set in the order they are bound to keys.
+    pub fn teams(&self) -> Vec<PadTeam> {
+        TEAMS.iter().cloned().filter(|&team| self.contains(team)).collect()
+    }
+}
+
+/// Where the player can move a pad to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PadMovement {
+    /// Stays on the tile it was placed on.
+    Fixed,
+    /// Moves left and right along its row.
+    Row,
+    /// Moves to any neighboring tile.
+    Free,
+}
+
+impl PadMovement {
+    pub fn allows(&self, direction: Direction) -> bool {
+        match *self {
+            PadMovement::Fixed => false,
+            PadMovement::Row => direction == Direction::Left || direction == Direction::Right,
+            PadMovement::Free => true,
+        }
+    }
+}
+
+/// Tiles around a pad that a hit reaches.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HitShape {
+    /// Only the pad's tile.
+    Tile,
+    /// The pad's tile and the ones next to it.
+    Plus,
+    /// `Plus` and the tiles next to two of its outer tiles, the 3x3 tiles around the pad on a
+    /// square grid.
+    Square,
+    /// The pad's whole row.
+    Row,
+    /// The pad's whole column, which runs at an angle on hex grids.
+    Column,
+    /// The pad's tile and the given number of tiles in a direction.
+    Line(Direction, i32),
+}
+
+impl HitShape {
+    /// Whether a hit centered on `center` reaches `tile`.
+    pub fn covers(&self, grid: &Grid, center: (i32, i32), tile: (i32, i32)) -> bool {
+        let (dx, dy) = (tile.0 - center.0, tile.1 - center.1);
+        match *self {
+            HitShape::Tile => tile == center,
+            HitShape::Plus => tile == center || grid.adjacent(center, tile),
+            HitShape::Square => {
+                let touching = grid.neighbors(center)
+                    .into_iter()
+                    .filter(|&next| grid.adjacent(next, tile))
+                    .count();
+                HitShape::Plus.covers(grid, center, tile) || touching >= 2
+            }
+            HitShape::Row => dy == 0,
+            HitShape::Column => dx == 0,
+            HitShape::Line(direction, length) => {
+                let (x, y) = direction.offset();
+                (0..length + 1).any(|i| (x * i, y * i) == (dx, dy))
+            }
         }
     }
 }
 
 pub struct Pad {
     pub pulse_timer: f32,
+    pub movement: PadMovement,
+    /// Whether the move keys control this pad, hitting a pad selects it.
+    pub selected: bool,
+    /// Move waiting for the next beat.
+    pub queued_move: Option<Direction>,
+    pub hit_shape: HitShape,
+    /// Beats after a hit before the pad can be hit again.
+    pub cooldown: i32,
+    pub cooldown_left: i32,
+    /// Time left to show the tiles reached by the last hit.
+    pub highlight_timer: f32,
+    /// Tile the last hit was centered on, the highlight stays there when the pad moves on.
+    pub hit_tile: Option<(i32, i32)>,
+}
+
+impl Pad {
+    pub fn new(movement: PadMovement, hit_shape: HitShape, cooldown: i32) -> Pad {
+        Pad {
+            pulse_timer: 0.0,
+            movement,
+            selected: false,
+            queued_move: None,
+            hit_shape,
+            cooldown,
+            cooldown_left: 0,
+            highlight_timer: 0.0,
+            hit_tile: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HitShape;
+    use grid::Grid;
+    use grid_shape::HexOrientation;
+    use terrain::Direction;
+
+    fn covered(grid: &Grid, shape: HitShape) -> usize {
+        let mut count = 0;
+        for x in 0..grid.width() {
+            for y in 0..grid.height() {
+                if shape.covers(grid, (3, 3), (x, y)) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn shapes_on_square_grids() {
+        let grid = Grid::new(7, 7, 10, 10);
+        assert_eq!(covered(&grid, HitShape::Tile), 1);
+        assert_eq!(covered(&grid, HitShape::Plus), 5);
+        assert_eq!(covered(&grid, HitShape::Square), 9);
+        assert_eq!(covered(&grid, HitShape::Row), 7);
+        assert_eq!(covered(&grid, HitShape::Column), 7);
+        assert_eq!(covered(&grid, HitShape::Line(Direction::Up, 2)), 3);
+        assert!(HitShape::Square.covers(&grid, (3, 3), (4, 4)));
+    }
+
+    #[test]
+    fn shapes_on_hex_grids() {
+        let grid = Grid::hex(7, 7, 10.0, HexOrientation::PointyTop);
+        assert_eq!(covered(&grid, HitShape::Plus), 7);
+        // the tiles next to the pad and the six between each pair of them
+        assert_eq!(covered(&grid, HitShape::Square), 13);
+        assert_eq!(covered(&grid, HitShape::Row), 7);
+        assert!(HitShape::Plus.covers(&grid, (3, 3), (4, 2)));
+        assert!(!HitShape::Plus.covers(&grid, (3, 3), (4, 4)));
+    }
 }
@@ -1,11 +1,14 @@
+use embla::math::Vec2;
+
 #[derive(PartialEq)]
 pub enum FillMode {
     Filled,
     Outline(f32),
 }
 
-pub struct ColoredRect {
-    pub rect: (f32, f32, f32, f32),
+pub struct ColoredPolygon {
+    /// Corners relative to the entity's position.
+    pub points: Vec<Vec2>,
     pub color: (f32, f32, f32, f32),
 }
 pub struct ColoredCircle {
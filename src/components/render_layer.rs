@@ -0,0 +1,12 @@
+/// Which layer an entity is drawn in, layers are drawn back to front in the order listed here.
+/// Within a layer things are drawn in the order they were submitted.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum RenderLayer {
+    Background,
+    Board,
+    Walls,
+    Markers,
+    Blobs,
+    Effects,
+    Hud,
+}
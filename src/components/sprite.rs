@@ -0,0 +1,12 @@
+/// An image drawn at the entity's position.
+#[derive(Clone)]
+pub struct Sprite {
+    pub image: String,
+    /// Size of a single frame if the image is a sprite sheet, frames are read left to right and
+    /// top to bottom.
+    pub frame_size: Option<(u32, u32)>,
+    pub frame: usize,
+    pub tint: (f32, f32, f32, f32),
+    pub scale: f32,
+    pub rotation: f32,
+}
@@ -0,0 +1,3 @@
+/// Switches blobs passing over it to the entity's `PadTeam`. Chords only switch to heading for the
+/// team's goal if the team is one of theirs, they never lose a team that has to hit them.
+pub struct TeamGate;
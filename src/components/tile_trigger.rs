@@ -1,5 +1,9 @@
-use components::PadTeam;
+use components::{HitShape, PadTeam};
 
 pub struct TileTrigger {
     pub team: PadTeam,
+    /// Tiles around the trigger's `TilePosition` that are hit.
+    pub shape: HitShape,
+    /// Beats since the pad was hit, triggers linger for a moment so chords can be hit.
+    pub age: f32,
 }
@@ -0,0 +1,166 @@
+use embla::input::{Input, Key, MouseButton};
+use embla::math::Vec2;
+use failure::{err_msg, Error};
+
+use components::PadTeam;
+use terrain::Direction;
+
+/// Game actions bound to a key press.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    HitPad(PadTeam),
+    /// Moves the selected pad on the next beat.
+    MovePad(Direction),
+    ToggleReduceMotion,
+    NextPalette,
+    ToggleTeamShapes,
+    ToggleDebugOverlay,
+    ZoomIn,
+    ZoomOut,
+    /// Zooms and pans back to the whole board.
+    ResetView,
+}
+
+static BINDINGS: &[(Key, Command, &str)] = &[
+    (Key::A, Command::HitPad(PadTeam::Blue), "blue"),
+    (Key::S, Command::HitPad(PadTeam::Red), "red"),
+    (Key::D, Command::HitPad(PadTeam::Green), "green"),
+    (Key::F, Command::HitPad(PadTeam::Yellow), "yellow"),
+    (Key::Left, Command::MovePad(Direction::Left), "left"),
+    (Key::Right, Command::MovePad(Direction::Right), "right"),
+    (Key::Up, Command::MovePad(Direction::Up), "up"),
+    (Key::Down, Command::MovePad(Direction::Down), "down"),
+    (Key::M, Command::ToggleReduceMotion, "motion"),
+    (Key::P, Command::NextPalette, "palette"),
+    (Key::T, Command::ToggleTeamShapes, "shapes"),
+    (Key::O, Command::ToggleDebugOverlay, "debug"),
+    (Key::Z, Command::ZoomIn, "zoom_in"),
+    (Key::X, Command::ZoomOut, "zoom_out"),
+    (Key::C, Command::ResetView, "reset_view"),
+];
+
+/// Everything the game reads from the input in a frame, so it can be recorded and replayed.
+#[derive(Clone)]
+pub struct Controls {
+    /// Mouse position in pixels from the top left corner.
+    pub mouse_position: Vec2,
+    pub place_wall: bool,
+    /// Dragging the mouse pans the view while this is set.
+    pub pan_view: bool,
+    /// Commands whose key was pressed this frame.
+    pub commands: Vec<Command>,
+    /// Commands whose key is held down, including ones pressed this frame.
+    pub down: Vec<Command>,
+}
+
+impl Controls {
+    pub fn from_input(input: &Input) -> Controls {
+        Controls {
+            mouse_position: input.mouse_position(),
+            place_wall: input.mouse_button_is_down(&MouseButton::Left),
+            pan_view: input.mouse_button_is_down(&MouseButton::Right),
+            commands: BINDINGS
+                .iter()
+                .filter(|(key, _, _)| input.key_is_pressed(key))
+                .map(|&(_, command, _)| command)
+                .collect(),
+            down: BINDINGS
+                .iter()
+                .filter(|(key, _, _)| input.key_is_down(key))
+                .map(|&(_, command, _)| command)
+                .collect(),
+        }
+    }
+
+    pub fn pressed(&self, command: Command) -> bool {
+        self.commands.contains(&command)
+    }
+
+    pub fn is_down(&self, command: Command) -> bool {
+        self.down.contains(&command)
+    }
+
+    /// Formats the controls as `<mouse x> <mouse y> <buttons> <commands...>`, where buttons has
+    /// bit 1 set for placing walls and bit 2 for panning. Commands that are held down are
+    /// prefixed with `+`.
+    pub fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} {} {}",
+            self.mouse_position.0,
+            self.mouse_position.1,
+            self.place_wall as i32 | (self.pan_view as i32) << 1
+        );
+        for &(_, command, name) in BINDINGS {
+            if self.pressed(command) {
+                line.push(' ');
+                line.push_str(name);
+            }
+        }
+        for &(_, command, name) in BINDINGS {
+            if self.is_down(command) {
+                line.push_str(" +");
+                line.push_str(name);
+            }
+        }
+        line
+    }
+
+    pub fn parse(line: &str) -> Result<Controls, Error> {
+        let mut tokens = line.split_whitespace();
+        let mut number = || -> Result<f32, Error> {
+            Ok(tokens
+                .next()
+                .ok_or_else(|| err_msg("expected `<mouse x> <mouse y> <buttons>`"))?
+                .parse::<f32>()?)
+        };
+        let mouse_position = Vec2::new(number()?, number()?);
+        let buttons = number()? as i32;
+
+        let mut commands = Vec::new();
+        let mut down = Vec::new();
+        for token in tokens {
+            // a single `+` marks a held command
+            let (list, name) = match token.strip_prefix('+') {
+                Some(name) => (&mut down, name),
+                None => (&mut commands, token),
+            };
+            let command = BINDINGS
+                .iter()
+                .find(|&&(_, _, n)| n == name)
+                .map(|&(_, command, _)| command)
+                .ok_or_else(|| err_msg(format!("unknown command `{}`", name)))?;
+            list.push(command);
+        }
+
+        Ok(Controls {
+            mouse_position,
+            place_wall: buttons & 1 != 0,
+            pan_view: buttons & 2 != 0,
+            commands,
+            down,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Controls};
+    use components::PadTeam;
+
+    #[test]
+    fn lines_round_trip() {
+        let controls = Controls::parse("10 20 1 red +red +blue").unwrap();
+        assert!(controls.place_wall);
+        assert!(controls.pressed(Command::HitPad(PadTeam::Red)));
+        assert!(controls.is_down(Command::HitPad(PadTeam::Blue)));
+        assert!(!controls.pressed(Command::HitPad(PadTeam::Blue)));
+
+        let line = controls.to_line();
+        assert_eq!(Controls::parse(&line).unwrap().to_line(), line);
+    }
+
+    #[test]
+    fn only_one_plus_marks_a_held_command() {
+        assert!(Controls::parse("0 0 0 ++red").is_err());
+    }
+}
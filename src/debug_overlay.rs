@@ -0,0 +1,245 @@
+use embla::ecs::{EntityId, World};
+use embla::math::Vec2;
+use failure::Error;
+
+use camera::Camera;
+use components::{Animation, Blob, BlobGoal, BlobSpawn, ColoredCircle, ColoredPolygon, Pad, PadTeam,
+                 Position, RenderLayer, Sprite, TeamGate, TilePosition, TileTrigger};
+use font::{text_width, GLYPH_HEIGHT};
+use grid::Grid;
+use render_interface::RenderInterface;
+
+// how often the fps counter is refreshed, in seconds
+const FPS_INTERVAL: f32 = 0.5;
+// size of a font dot in pixels
+const TEXT_SIZE: f32 = 2.0;
+const TEXT_COLOR: (f32, f32, f32, f32) = (1.0, 1.0, 1.0, 1.0);
+const PANEL_COLOR: (f32, f32, f32, f32) = (0.0, 0.0, 0.0, 0.7);
+const PATH_COLOR: (f32, f32, f32, f32) = (1.0, 0.3, 1.0, 0.8);
+const OCCUPIED_COLOR: (f32, f32, f32, f32) = (1.0, 0.2, 0.2, 0.8);
+const INSPECT_COLOR: (f32, f32, f32, f32) = (1.0, 1.0, 0.2, 1.0);
+
+/// Timing of the current frame shown by the overlay.
+pub struct BeatInfo {
+    pub beat: i32,
+    pub beats_per_bar: i32,
+    /// How far into the current beat we are, from 0 to 1.
+    pub phase: f32,
+}
+
+/// Toggleable overlay showing blob paths, occupied tiles, timing and entity counts, with an
+/// inspector listing the entities on a clicked tile.
+pub struct DebugOverlay {
+    pub enabled: bool,
+    inspected_tile: Option<(i32, i32)>,
+    frames: i32,
+    frame_time: f32,
+    fps: f32,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            enabled: false,
+            inspected_tile: None,
+            frames: 0,
+            frame_time: 0.0,
+            fps: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.frames += 1;
+        self.frame_time += dt;
+        if self.frame_time >= FPS_INTERVAL {
+            self.fps = self.frames as f32 / self.frame_time;
+            self.frames = 0;
+            self.frame_time = 0.0;
+        }
+    }
+
+    pub fn inspect(&mut self, tile: (i32, i32)) {
+        self.inspected_tile = Some(tile);
+    }
+
+    pub fn render(
+        &self,
+        camera: &Camera,
+        grid: &Grid,
+        world: &World,
+        beat: &BeatInfo,
+        renderer: &mut RenderInterface,
+    ) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        renderer.set_layer(RenderLayer::Hud);
+        let pixel = 1.0 / camera.scale();
+        for (position, blob) in world.with_components::<(Position, Blob)>() {
+            let mut from = position.0;
+            for &tile in blob.path.iter().skip(blob.path_index) {
+                let to = grid.cell_center(tile);
+                renderer.draw_line(from, to, 2.0 * pixel, PATH_COLOR)?;
+                from = to;
+            }
+        }
+
+        for x in 0..grid.width() {
+            for y in 0..grid.height() {
+                if grid.occupied(&(x, y)) {
+                    // a cross between opposite corners
+                    let corners = grid.cell_corners((x, y));
+                    let half = corners.len() / 2;
+                    for i in 0..2 {
+                        let (a, b) = (corners[i], corners[i + half]);
+                        renderer.draw_line(a, b, 2.0 * pixel, OCCUPIED_COLOR)?;
+                    }
+                }
+            }
+        }
+
+        let mut lines = vec![
+            format!("FPS {:.0}", self.fps),
+            format!(
+                "BEAT {} BAR {}.{} PHASE {:.2}",
+                beat.beat,
+                beat.beat / beat.beats_per_bar + 1,
+                beat.beat % beat.beats_per_bar + 1,
+                beat.phase
+            ),
+            String::new(),
+        ];
+        let components = component_entities(world);
+        for &(name, ref entities) in &components {
+            lines.push(format!("{} {}", name, entities.len()));
+        }
+
+        if let Some(tile) = self.inspected_tile {
+            let corners = grid.cell_corners(tile);
+            for i in 0..corners.len() {
+                let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                renderer.draw_line(a, b, 2.0 * pixel, INSPECT_COLOR)?;
+            }
+
+            lines.push(String::new());
+            lines.push(format!("TILE {}, {}", tile.0, tile.1));
+            for (e, pos) in world.with_components::<(EntityId, TilePosition)>() {
+                if (pos.0, pos.1) != tile {
+                    continue;
+                }
+                let names = components
+                    .iter()
+                    .filter(|(_, entities)| entities.contains(&e.0))
+                    .map(|&(name, _)| name)
+                    .collect::<Vec<_>>();
+                lines.push(format!("#{} {}", e.0, names.join(" ")));
+            }
+        }
+
+        // text panel in the top left corner of the viewport
+        let viewport = camera.viewport();
+        let top_left = camera.screen_to_world(Vec2::new(viewport.0, viewport.3));
+        let size = TEXT_SIZE * pixel;
+        let line_height = (GLYPH_HEIGHT + 2) as f32 * size;
+        let width = lines
+            .iter()
+            .map(|l| text_width(l, size))
+            .fold(0.0, f32::max);
+        let panel = (
+            top_left.0,
+            top_left.1 - line_height * lines.len() as f32 - size * 2.0,
+            top_left.0 + width + size * 4.0,
+            top_left.1,
+        );
+        renderer.draw_rect(panel, PANEL_COLOR)?;
+        for (i, line) in lines.iter().enumerate() {
+            let position = Vec2::new(
+                top_left.0 + size * 2.0,
+                top_left.1 - line_height * (i + 1) as f32,
+            );
+            renderer.draw_text(line, position, size, TEXT_COLOR)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Entities having each component type, by component name. Every type in `components` that is
+/// added to entities has to be listed here, which the tests check.
+fn component_entities(world: &World) -> Vec<(&'static str, Vec<usize>)> {
+    fn entities<T: 'static>(world: &World) -> Vec<usize> {
+        world
+            .with_components::<(EntityId, T)>()
+            .map(|(e, _)| e.0)
+            .collect()
+    }
+
+    vec![
+        ("ANIMATION", entities::<Animation>(world)),
+        ("BLOB", entities::<Blob>(world)),
+        ("BLOBGOAL", entities::<BlobGoal>(world)),
+        ("BLOBSPAWN", entities::<BlobSpawn>(world)),
+        ("COLOREDCIRCLE", entities::<ColoredCircle>(world)),
+        ("COLOREDPOLYGON", entities::<ColoredPolygon>(world)),
+        ("PAD", entities::<Pad>(world)),
+        ("PADTEAM", entities::<PadTeam>(world)),
+        ("POSITION", entities::<Position>(world)),
+        ("RENDERLAYER", entities::<RenderLayer>(world)),
+        ("SPRITE", entities::<Sprite>(world)),
+        ("TEAMGATE", entities::<TeamGate>(world)),
+        ("TILEPOSITION", entities::<TilePosition>(world)),
+        ("TILETRIGGER", entities::<TileTrigger>(world)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use embla::ecs::World;
+
+    use super::component_entities;
+
+    // types in the components module that are only used as fields of components
+    static NOT_COMPONENTS: &[&str] = &[
+        "AnimationClip",
+        "AnimationState",
+        "AnimationTiming",
+        "FillMode",
+        "HitShape",
+        "Note",
+        "PadMovement",
+        "TeamSet",
+    ];
+
+    #[test]
+    fn every_component_is_listed() {
+        let listed = component_entities(&World::new())
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/components");
+        for entry in fs::read_dir(dir).unwrap() {
+            let source = fs::read_to_string(entry.unwrap().path()).unwrap();
+            for line in source.lines() {
+                let name = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["pub", "struct", name, ..] | ["pub", "enum", name, ..] => name
+                        .split(|c: char| !c.is_alphanumeric())
+                        .next()
+                        .unwrap(),
+                    _ => continue,
+                };
+                if NOT_COMPONENTS.contains(&name) {
+                    continue;
+                }
+                assert!(
+                    listed.contains(&name.to_uppercase().as_str()),
+                    "component {} is missing from the debug overlay",
+                    name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use grid::{Grid, PathNode};
+
+/// The cheapest next step towards a goal from everywhere on the grid.
+///
+/// Built with a single Dijkstra search backwards from the goal, so every blob heading for the same
+/// goal can share it and look up its next step every beat instead of running A* for each of them.
+/// Paths read from it are as cheap as any other, but where several are equally cheap it may pick a
+/// different one than `Grid::find_path`.
+pub struct FlowField {
+    goal: (i32, i32),
+    next: HashMap<PathNode, PathNode>,
+}
+
+impl FlowField {
+    pub fn new(grid: &Grid, goal: (i32, i32)) -> FlowField {
+        // find every node that can be reached from standing on any tile, along with the steps
+        // leading into it
+        let mut incoming: HashMap<PathNode, Vec<(PathNode, i32)>> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut open = Vec::new();
+        for x in 0..grid.width() {
+            for y in 0..grid.height() {
+                seen.insert(((x, y), (0, 0)));
+                open.push(((x, y), (0, 0)));
+            }
+        }
+        while let Some(node) = open.pop() {
+            for (step, cost) in grid.steps(node, goal) {
+                incoming.entry(step).or_default().push((node, cost));
+                if seen.insert(step) {
+                    open.push(step);
+                }
+            }
+        }
+
+        let end = (goal, (0, 0));
+        let mut costs = HashMap::new();
+        let mut next = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        costs.insert(end, 0);
+        queue.push((Reverse(0), end));
+        while let Some((Reverse(cost), node)) = queue.pop() {
+            if cost > costs[&node] {
+                continue;
+            }
+            for &(prev, step_cost) in incoming.get(&node).into_iter().flat_map(|s| s.iter()) {
+                let total = cost + step_cost;
+                if costs.get(&prev).is_none_or(|&old| total < old) {
+                    costs.insert(prev, total);
+                    next.insert(prev, node);
+                    queue.push((Reverse(total), prev));
+                }
+            }
+        }
+
+        FlowField { goal, next }
+    }
+
+    /// Follows the field from `start`, returns `None` if the goal can't be reached from there.
+    pub fn path(&self, start: PathNode) -> Option<Vec<(i32, i32)>> {
+        let mut node = start;
+        let mut path = vec![start.0];
+        while node.0 != self.goal {
+            node = *self.next.get(&node)?;
+            path.push(node.0);
+        }
+
+        Some(path)
+    }
+}
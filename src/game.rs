@@ -1,146 +1,506 @@
 use embla::ecs::World;
-use embla::input::{Input, MouseButton};
+use embla::input::Input;
 use embla::math::Vec2;
 use failure::Error;
 
+use camera::Camera;
+use controls::{Command, Controls};
+use debug_overlay::{BeatInfo, DebugOverlay};
+use font::{text_width, GLYPH_HEIGHT};
 use grid::Grid;
+use level::Level;
+use palette::Palette;
 use render_interface::RenderInterface;
+use screen_shake::{ScreenShake, ShakeSettings};
+use sprites::Sprites;
 use systems;
+use terrain::{Direction, Terrain};
+use theme::{Theme, ThemeWatcher, THEME_PATH};
+use ui::Ui;
 
-use components::{BlobGoal, BlobSpawn, ColoredCircle, ColoredRect, FillMode, Pad, PadTeam,
-                 Position, TilePosition};
+use components::{Animation, BlobGoal, BlobSpawn, ColoredCircle, ColoredPolygon, FillMode, Note, Pad,
+                 PadTeam, Position, RenderLayer, Sprite, TeamGate,
+                 TilePosition};
 
 const BEAT_TIME: f32 = 0.25;
+const BEATS_PER_BAR: i32 = 4;
+// trauma added for every blob that makes it to its goal
+const GOAL_TRAUMA: f32 = 0.4;
+// space around the board in tiles
+const BOARD_MARGIN: f32 = 1.0;
+// distance of the settings panel and score from the edge of the screen in pixels
+const PANEL_INSET: f32 = 8.0;
+// size of a dot of the score font in pixels
+const SCORE_DOT_SIZE: f32 = 3.0;
+// how much a zoom command zooms in or out
+const ZOOM_STEP: f32 = 1.25;
+const MAX_ZOOM: f32 = 4.0;
 
 pub struct Game {
     grid: Grid,
     hovered_tile: Option<(i32, i32)>,
-    screen_size: Vec2,
+    camera: Camera,
+    /// World position held under the mouse while the view is dragged.
+    pan_anchor: Option<Vec2>,
+    screen_shake: ScreenShake,
+    palette: Palette,
+    team_shapes: bool,
+    debug_overlay: DebugOverlay,
+    theme: Theme,
+    theme_watcher: ThemeWatcher,
+    sprites: Sprites,
+    blob_sprite: Option<Sprite>,
+    blob_animation: Option<Animation>,
     world: World,
     beat_timer: f32,
+    beat: i32,
+    /// One point for every blob hit, long blobs let go early give partial credit.
+    score: f32,
 }
 
 impl Game {
-    pub fn new() -> Result<Game, Error> {
+    pub fn new(level: &Level) -> Result<Game, Error> {
+        let grid = level.grid();
+
+        let (board_min, board_max) = grid.bounds();
+        let board_size = board_max - board_min;
+        let margin = Vec2::new(
+            grid.cell_width() * BOARD_MARGIN,
+            grid.cell_height() * BOARD_MARGIN,
+        );
+        let mut camera = Camera::new(board_size + margin * 2.0);
+        camera.center_on(board_min + board_size / 2.0);
+
         let mut game = Game {
-            grid: Grid::new(9, 10, 40, 40),
+            grid,
             hovered_tile: None,
-            screen_size: Vec2::new(0.0, 0.0),
+            camera,
+            pan_anchor: None,
+            screen_shake: ScreenShake::new(ShakeSettings::default()),
+            palette: Palette::Default,
+            team_shapes: false,
+            debug_overlay: DebugOverlay::new(),
+            theme: Theme::load(THEME_PATH)?,
+            theme_watcher: ThemeWatcher::new(THEME_PATH),
+            sprites: Sprites::new(),
+            blob_sprite: None,
+            blob_animation: None,
             world: World::new(),
             beat_timer: 0.0,
+            beat: 0,
+            score: 0.0,
         };
 
-        game.init()?;
+        game.init(level)?;
 
         Ok(game)
     }
 
-    fn init(&mut self) -> Result<(), Error> {
-        self.insert_pad(1, 1, PadTeam::Blue)?;
-        self.insert_pad(3, 1, PadTeam::Red)?;
-        self.insert_pad(5, 1, PadTeam::Green)?;
-        self.insert_pad(7, 1, PadTeam::Yellow)?;
+    fn init(&mut self, level: &Level) -> Result<(), Error> {
+        self.load_blob_sprite()?;
 
-        self.insert_spawn(1, 9, PadTeam::Blue, 2)?;
-        self.insert_spawn(3, 9, PadTeam::Red, 4)?;
-        self.insert_spawn(5, 9, PadTeam::Green, 6)?;
-        self.insert_spawn(7, 9, PadTeam::Yellow, 8)?;
+        for pad in &level.pads {
+            let (x, y) = pad.tile;
+            let team = pad.team;
+            self.insert_pad(x, y, team, Pad::new(pad.movement, pad.hit_shape, pad.cooldown))?;
+        }
+        for spawn in &level.spawns {
+            let (x, y) = spawn.tile;
+            self.insert_spawn(x, y, spawn.team, spawn.interval, spawn.chart.clone())?;
+        }
+        for &((x, y), team) in &level.goals {
+            self.insert_goal(x, y, team)?;
+        }
+        for &((x, y), team) in &level.gates {
+            self.insert_gate(x, y, team)?;
+        }
+        for &tile in &level.walls {
+            self.insert_wall(tile)?;
+        }
 
-        self.insert_goal(1, 0, PadTeam::Blue)?;
-        self.insert_goal(3, 0, PadTeam::Red)?;
-        self.insert_goal(5, 0, PadTeam::Green)?;
-        self.insert_goal(7, 0, PadTeam::Yellow)?;
+        // place everything before the first frame is drawn
+        systems::grid_positioning(&self.grid, &mut self.world)?;
 
         Ok(())
     }
 
-    pub fn update(&mut self, dt: f32, input: &Input) -> Result<(), Error> {
-        let mut mouse_position = input.mouse_position();
-        mouse_position.1 = self.screen_size.1 as f32 - mouse_position.1;
+    /// Sets the window size that mouse positions in the controls are relative to.
+    pub fn set_screen_size(&mut self, screen_size: (i32, i32)) {
+        self.camera.set_screen_size(screen_size);
+    }
+
+    pub fn update(&mut self, dt: f32, controls: &Controls) -> Result<(), Error> {
+        match self.theme_watcher.poll(dt) {
+            Some(Ok(theme)) => {
+                self.theme = theme;
+                // a broken sprite keeps the one that was loaded before
+                if let Err(e) = self.load_blob_sprite() {
+                    eprintln!("Failed to reload blob sprite: {}", e);
+                }
+                systems::apply_theme(&self.theme, &self.grid, &mut self.world)?;
+            }
+            // keep the old theme around while the file is being edited
+            Some(Err(e)) => eprintln!("Failed to reload theme: {}", e),
+            None => {}
+        }
+
+        self.update_view(controls);
+
+        let mouse_position = self.camera.mouse_to_world(controls.mouse_position);
         self.hovered_tile = self.grid.tile_at(mouse_position);
 
         if let Some(tile) = self.hovered_tile {
-            if input.mouse_button_is_down(&MouseButton::Left) {
+            // clicking inspects tiles instead of building while the overlay is up
+            if controls.place_wall && self.debug_overlay.enabled {
+                self.debug_overlay.inspect(tile);
+            } else if controls.place_wall {
                 self.insert_wall(tile)?;
             }
         }
 
+        if controls.pressed(Command::ToggleReduceMotion) {
+            let settings = &mut self.screen_shake.settings;
+            settings.reduce_motion = !settings.reduce_motion;
+        }
+        if controls.pressed(Command::NextPalette) {
+            self.palette = self.palette.next();
+        }
+        if controls.pressed(Command::ToggleTeamShapes) {
+            self.team_shapes = !self.team_shapes;
+        }
+        if controls.pressed(Command::ToggleDebugOverlay) {
+            self.debug_overlay.enabled = !self.debug_overlay.enabled;
+        }
+        self.debug_overlay.update(dt);
+
+        let beat = self.beat;
         self.beat_timer += dt;
         // On beat
         while self.beat_timer > BEAT_TIME {
             self.beat_timer -= BEAT_TIME;
+            self.beat += 1;
+
+            if self.beat % BEATS_PER_BAR == 0 {
+                self.screen_shake.kick(Vec2::new(0.0, -1.0));
+            }
+
+            let reached_goal = systems::move_blobs(&self.grid, &mut self.world)?;
+            self.screen_shake.add_trauma(reached_goal as f32 * GOAL_TRAUMA);
 
-            systems::move_blobs(&mut self.world)?;
-            systems::spawn_blobs(&self.grid, &mut self.world)?;
+            systems::spawn_blobs(
+                &self.grid,
+                &self.theme,
+                self.blob_sprite.as_ref(),
+                self.blob_animation.as_ref(),
+                &mut self.world,
+            )?;
         }
+        self.screen_shake.update(dt);
+
+        let beat_position = self.beat as f32 + self.beat_timer / BEAT_TIME;
+        systems::animate_sprites(dt, beat_position, BEAT_TIME, &mut self.world)?;
         systems::grid_positioning(&self.grid, &mut self.world)?;
 
         // do all tweening after grid positioning
         let beat_ratio = self.beat_timer / BEAT_TIME;
         systems::tween_blobs(beat_ratio, &self.grid, &mut self.world)?;
+        systems::stack_blobs(&self.grid, &mut self.world)?;
+
+        let on_beat = self.beat != beat;
+        systems::pad_update(
+            dt,
+            on_beat,
+            controls,
+            &self.grid,
+            &self.theme,
+            &mut self.world,
+        )?;
 
-        systems::pad_update(dt, input, &self.grid, &mut self.world)?;
+        self.score += systems::trigger_blobs(dt / BEAT_TIME, &self.grid, &mut self.world)?;
+        self.score += systems::hold_blobs(dt / BEAT_TIME, controls, &self.grid, &mut self.world)?;
 
-        systems::trigger_blobs(&mut self.world)?;
+        systems::team_colors(self.palette, &mut self.world)?;
 
         Ok(())
     }
 
+    fn update_view(&mut self, controls: &Controls) {
+        let zoom = if controls.pressed(Command::ZoomIn) {
+            (self.camera.zoom() * ZOOM_STEP).min(MAX_ZOOM)
+        } else if controls.pressed(Command::ZoomOut) {
+            (self.camera.zoom() / ZOOM_STEP).max(1.0)
+        } else {
+            self.camera.zoom()
+        };
+        if zoom != self.camera.zoom() {
+            // zoom around the mouse so the tile under it stays put
+            let before = self.camera.mouse_to_world(controls.mouse_position);
+            self.camera.set_zoom(zoom);
+            let after = self.camera.mouse_to_world(controls.mouse_position);
+            self.camera.pan(before - after);
+        }
+
+        if controls.pressed(Command::ResetView) {
+            let (board_min, board_max) = self.grid.bounds();
+            self.camera.set_zoom(1.0);
+            self.camera.center_on((board_min + board_max) / 2.0);
+        }
+
+        let mouse_position = self.camera.mouse_to_world(controls.mouse_position);
+        self.pan_anchor = if controls.pan_view {
+            if let Some(anchor) = self.pan_anchor {
+                self.camera.pan(anchor - mouse_position);
+            }
+            self.pan_anchor.or(Some(mouse_position))
+        } else {
+            None
+        };
+    }
+
     pub fn render(&mut self, renderer: &mut RenderInterface) -> Result<(), Error> {
-        let screen_size = renderer.screen_size();
-        self.screen_size = Vec2::new(screen_size.0 as f32, screen_size.1 as f32);
+        self.camera.set_screen_size(renderer.screen_size());
+        renderer.set_clear_color(self.theme.clear_color);
+        renderer.set_camera(&self.camera)?;
+
+        // the backdrop and letterbox bars cover the whole screen, the world is clipped to the
+        // viewport so it never spills into the bars
+        let viewport = self.camera.world_viewport();
+        renderer.set_offset(Vec2::zero());
+        renderer.set_layer(RenderLayer::Background);
+        renderer.draw_rect(viewport, self.theme.clear_color)?;
+        for bar in self.camera.letterbox() {
+            renderer.draw_rect(bar, self.theme.clear_color)?;
+        }
+        renderer.set_clip(Some(viewport));
+        renderer.set_offset(self.screen_shake.offset());
 
         // render grid
+        renderer.set_layer(RenderLayer::Board);
         for x in 0..self.grid.width() {
             for y in 0..self.grid.height() {
-                let r = self.grid.cell_rect(x, y);
-                let r = (r.0 as f32, r.1 as f32, r.2 as f32, r.3 as f32);
-
+                let terrain = self.grid.terrain(&(x, y));
                 let color = if self.hovered_tile == Some((x, y)) {
-                    (0.8, 0.8, 0.8, 1.0)
+                    self.theme.grid_hover_color
                 } else {
-                    (0.3, 0.3, 0.3, 1.0)
+                    match terrain {
+                        Some(Terrain::Mud) => self.theme.mud_color,
+                        Some(Terrain::Ice) => self.theme.ice_color,
+                        Some(Terrain::Conveyor(_)) => self.theme.conveyor_color,
+                        Some(Terrain::OneWay(_)) => self.theme.one_way_color,
+                        None => self.theme.grid_color,
+                    }
                 };
-                renderer.draw_rect((r.0 + 1.0, r.1 + 1.0, r.2 - 1.0, r.3 - 1.0), color)?;
+                let outline = self.grid.cell_outline((x, y), self.theme.grid_gap);
+                renderer.draw_polygon(&outline, color)?;
+
+                match terrain {
+                    Some(Terrain::Conveyor(direction)) | Some(Terrain::OneWay(direction)) => {
+                        let center = self.grid.cell_center((x, y));
+                        let size = self.grid.cell_width() * 0.25;
+                        draw_arrow(renderer, center, direction, size, self.theme.arrow_color)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // teleporter ends are numbered by pair
+        for (i, &(a, b)) in self.grid.teleporters().iter().enumerate() {
+            let label = (i + 1).to_string();
+            let color = self.theme.teleporter_color;
+            for &(x, y) in &[a, b] {
+                let center = self.grid.cell_center((x, y));
+                let radius = self.grid.cell_width() * 0.4;
+                renderer.draw_ring(center, radius, radius * 0.2, color)?;
+
+                let size = radius * 0.15;
+                let offset = Vec2::new(text_width(&label, size), GLYPH_HEIGHT as f32 * size) / 2.0;
+                renderer.draw_text(&label, center - offset, size, color)?;
             }
         }
 
+        systems::render_hit_areas(
+            &self.grid,
+            self.palette,
+            &self.theme,
+            &mut self.world,
+            renderer,
+        )?;
+        systems::render_long_blobs(
+            &self.grid,
+            self.palette,
+            &self.theme,
+            &mut self.world,
+            renderer,
+        )?;
         systems::render_primitives(&mut self.world, renderer)?;
+        systems::render_chord_blobs(self.palette, &self.theme, &mut self.world, renderer)?;
+        systems::render_selected_pads(
+            &self.grid,
+            self.palette,
+            &self.theme,
+            &mut self.world,
+            renderer,
+        )?;
+        systems::render_sprites(&self.sprites, &mut self.world, renderer)?;
+        if self.team_shapes {
+            systems::render_team_shapes(&self.grid, &self.theme, &mut self.world, renderer)?;
+        }
+
+        renderer.set_offset(Vec2::zero());
+        renderer.set_clip(None);
+        renderer.set_layer(RenderLayer::Hud);
+
+        // score in the top right corner of the viewport
+        let viewport = self.camera.viewport();
+        let size = SCORE_DOT_SIZE / self.camera.scale();
+        let inset = PANEL_INSET / self.camera.scale();
+        let label = format!("SCORE {:.1}", self.score);
+        let corner = self.camera.screen_to_world(Vec2::new(viewport.2, viewport.3));
+        let extent = Vec2::new(text_width(&label, size), GLYPH_HEIGHT as f32 * size);
+        let position = corner - extent - Vec2::new(inset, inset);
+        renderer.draw_text(&label, position, size, self.theme.hud_color)?;
+
+        let beat = BeatInfo {
+            beat: self.beat,
+            beats_per_bar: BEATS_PER_BAR,
+            phase: self.beat_timer / BEAT_TIME,
+        };
+        self.debug_overlay
+            .render(&self.camera, &self.grid, &self.world, &beat, renderer)?;
 
         Ok(())
     }
 
+    /// Shows the settings, changes are turned into commands so they end up in replays. The
+    /// caller can add its own widgets below before ending the frame with `Ui::end`.
+    pub fn settings_panel(&self, input: &Input, ui: &mut Ui, controls: &mut Controls) {
+        // inset from the top left corner of the viewport by a few pixels
+        let viewport = self.camera.viewport();
+        let inset = PANEL_INSET / self.camera.scale();
+        let origin = self.camera.screen_to_world(Vec2::new(viewport.0, viewport.3));
+        let origin = origin + Vec2::new(inset, -inset);
+        ui.begin(input, &self.camera, origin);
+
+        ui.label("SETTINGS");
+        let mut commands = Vec::new();
+        ui.row(|ui| {
+            ui.column(|ui| {
+                let mut toggle = |ui: &mut Ui, label: &str, mut value: bool, command| {
+                    if ui.checkbox(label, &mut value) {
+                        commands.push(command);
+                    }
+                };
+                toggle(
+                    ui,
+                    "REDUCE MOTION",
+                    self.screen_shake.settings.reduce_motion,
+                    Command::ToggleReduceMotion,
+                );
+                toggle(ui, "TEAM SHAPES", self.team_shapes, Command::ToggleTeamShapes);
+                toggle(
+                    ui,
+                    "DEBUG OVERLAY",
+                    self.debug_overlay.enabled,
+                    Command::ToggleDebugOverlay,
+                );
+
+                // the slider picks a number of zoom steps, so replays stay key presses
+                let levels = (MAX_ZOOM.ln() / ZOOM_STEP.ln()).round();
+                let current = (self.camera.zoom().ln() / ZOOM_STEP.ln()).round();
+                let mut level = current;
+                if ui.slider("ZOOM", &mut level, 0.0, levels) {
+                    let steps = level.round() - current;
+                    let command = if steps > 0.0 {
+                        Command::ZoomIn
+                    } else {
+                        Command::ZoomOut
+                    };
+                    for _ in 0..steps.abs() as usize {
+                        commands.push(command);
+                    }
+                }
+            });
+
+            ui.column(|ui| {
+                let mut palettes = vec![Palette::Default];
+                while palettes[palettes.len() - 1].next() != Palette::Default {
+                    let next = palettes[palettes.len() - 1].next();
+                    palettes.push(next);
+                }
+                let names = palettes
+                    .iter()
+                    .map(|p| format!("{:?}", p).to_uppercase())
+                    .collect::<Vec<_>>();
+                let items = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+                let current = palettes.iter().position(|&p| p == self.palette).unwrap();
+                let mut selected = current;
+
+                ui.label("PALETTE");
+                if ui.list("PALETTE", &items, &mut selected) {
+                    // palettes can only be cycled through, so replays stay key presses
+                    let steps = (selected + palettes.len() - current) % palettes.len();
+                    for _ in 0..steps {
+                        commands.push(Command::NextPalette);
+                    }
+                }
+            });
+        });
+        controls.commands.extend(commands);
+    }
+
     fn insert_wall(&mut self, (x, y): (i32, i32)) -> Result<(), Error> {
         let size = (self.grid.cell_width(), self.grid.cell_height());
-        let rect = (1.0, 1.0, size.0 as f32 - 1.0, size.1 as f32 - 1.0);
-        self.world
+        let points = self.grid.centered_outline((x, y), self.theme.grid_gap);
+        let sprite = self.load_sprite(
+            self.theme.wall_sprite.clone(),
+            None,
+            size.0,
+            self.theme.wall_color,
+        )?;
+
+        let entity = self.world
             .add_entity()
             .insert(Position(Vec2::zero()))
             .insert(TilePosition(x, y))
-            .insert(ColoredRect {
-                rect,
-                color: (1.0, 1.0, 1.0, 1.0),
-            });
+            .insert(RenderLayer::Walls);
+        match sprite {
+            Some(sprite) => entity.insert(sprite),
+            None => entity.insert(ColoredPolygon {
+                points,
+                color: self.theme.wall_color,
+            }),
+        };
 
         self.grid.set_occupied((x, y), true);
 
         Ok(())
     }
 
-    fn insert_spawn(&mut self, x: i32, y: i32, team: PadTeam, interval: i32) -> Result<(), Error> {
+    fn insert_spawn(
+        &mut self,
+        x: i32,
+        y: i32,
+        team: PadTeam,
+        interval: i32,
+        chart: Vec<Note>,
+    ) -> Result<(), Error> {
         self.world
             .add_entity()
             .insert(Position(Vec2::zero()))
             .insert(TilePosition(x, y))
+            .insert(RenderLayer::Markers)
             .insert(team)
             .insert(ColoredCircle {
-                radius: self.grid.cell_width() as f32 * 0.35,
+                radius: self.grid.cell_width() * self.theme.spawn_radius,
                 color: team.color(),
-                fill: FillMode::Outline(2.0),
+                fill: FillMode::Outline(self.theme.spawn_outline),
             })
             .insert(BlobSpawn {
-                interval: interval,
+                interval,
                 timer: interval,
+                chart,
+                chart_index: 0,
             });
 
         Ok(())
@@ -151,30 +511,193 @@ impl Game {
             .add_entity()
             .insert(Position(Vec2::zero()))
             .insert(TilePosition(x, y))
+            .insert(RenderLayer::Markers)
             .insert(team)
             .insert(ColoredCircle {
-                radius: self.grid.cell_width() as f32 * 0.45,
+                radius: self.grid.cell_width() * self.theme.goal_radius,
                 color: team.color(),
-                fill: FillMode::Outline(2.0),
+                fill: FillMode::Outline(self.theme.goal_outline),
             })
             .insert(BlobGoal);
 
         Ok(())
     }
 
-    fn insert_pad(&mut self, x: i32, y: i32, team: PadTeam) -> Result<(), Error> {
+    fn insert_gate(&mut self, x: i32, y: i32, team: PadTeam) -> Result<(), Error> {
         self.world
             .add_entity()
             .insert(Position(Vec2::zero()))
             .insert(TilePosition(x, y))
+            .insert(RenderLayer::Markers)
+            .insert(team)
             .insert(ColoredCircle {
-                radius: self.grid.cell_width() as f32 * 0.35,
+                radius: self.grid.cell_width() * self.theme.gate_radius,
                 color: team.color(),
-                fill: FillMode::Outline(6.0),
+                fill: FillMode::Outline(self.theme.gate_outline),
             })
+            .insert(TeamGate);
+
+        Ok(())
+    }
+
+    fn insert_pad(
+        &mut self,
+        x: i32,
+        y: i32,
+        team: PadTeam,
+        pad: Pad,
+    ) -> Result<(), Error> {
+        let radius = self.grid.cell_width() * self.theme.pad_radius;
+        let sprite = self.load_sprite(
+            self.theme.pad_sprite.clone(),
+            self.theme.pad_sprite_frame,
+            radius * 2.0,
+            team.color(),
+        )?;
+
+        let entity = self.world
+            .add_entity()
+            .insert(Position(Vec2::zero()))
+            .insert(TilePosition(x, y))
+            .insert(RenderLayer::Markers)
             .insert(team)
-            .insert(Pad { pulse_timer: 0.0 });
+            .insert(pad);
+        match sprite {
+            Some(sprite) => entity
+                .insert(sprite)
+                .insert(Animation::new(self.theme.pad_animations.clone())),
+            None => entity.insert(ColoredCircle {
+                radius,
+                color: team.color(),
+                fill: FillMode::Outline(self.theme.pad_outline),
+            }),
+        };
+
+        Ok(())
+    }
+
+    fn load_blob_sprite(&mut self) -> Result<(), Error> {
+        let size = self.theme.blob_radius * 2.0;
+        let path = self.theme.blob_sprite.clone();
+        let frame_size = self.theme.blob_sprite_frame;
+        self.blob_sprite = self.load_sprite(path, frame_size, size, (1.0, 1.0, 1.0, 1.0))?;
+        self.blob_animation = match self.blob_sprite {
+            Some(_) => Some(Animation::new(self.theme.blob_animations.clone())),
+            None => None,
+        };
 
         Ok(())
     }
+
+    /// Loads the image at `path` into a sprite scaled so a frame is `size` wide.
+    fn load_sprite(
+        &mut self,
+        path: Option<String>,
+        frame_size: Option<(u32, u32)>,
+        size: f32,
+        tint: (f32, f32, f32, f32),
+    ) -> Result<Option<Sprite>, Error> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let width = self.sprites.load(&path, frame_size)?[0].image().width;
+
+        Ok(Some(Sprite {
+            image: path,
+            frame_size,
+            frame: 0,
+            tint,
+            scale: size / width as f32,
+            rotation: 0.0,
+        }))
+    }
+}
+
+/// Draws a chevron pointing in `direction`.
+fn draw_arrow(
+    renderer: &mut RenderInterface,
+    center: Vec2,
+    direction: Direction,
+    size: f32,
+    color: (f32, f32, f32, f32),
+) -> Result<(), Error> {
+    let offset = direction.offset();
+    let forward = Vec2::new(offset.0 as f32, offset.1 as f32) * size;
+    let side = Vec2::new(-forward.1, forward.0);
+    let tip = center + forward * 0.5;
+    let width = size * 0.3;
+    renderer.draw_capsule(tip, tip - forward + side, width / 2.0, color)?;
+    renderer.draw_capsule(tip, tip - forward - side, width / 2.0, color)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Game;
+    use components::{Pad, PadTeam, RenderLayer, TilePosition};
+    use controls::Controls;
+    use level::{Level, LEVEL_PATH};
+    use recording_renderer::RecordingRenderer;
+
+    fn render(game: &mut Game) -> RecordingRenderer {
+        game.update(0.0, &Controls::parse("0 0 0").unwrap()).unwrap();
+        let mut renderer = RecordingRenderer::new((640, 480));
+        game.render(&mut renderer).unwrap();
+        renderer
+    }
+
+    #[test]
+    fn clear_color_comes_from_the_theme() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        let renderer = render(&mut game);
+
+        assert_eq!(renderer.clear_color(), game.theme.clear_color);
+    }
+
+    #[test]
+    fn the_world_is_drawn_over_a_backdrop_and_clipped_to_the_viewport() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        let renderer = render(&mut game);
+
+        // the backdrop and a bar on either side of the board
+        let clear_color = game.theme.clear_color;
+        renderer.assert_count(3, "backdrop or letterbox bar", |d| {
+            d.layer == RenderLayer::Background && d.command.has_color(clear_color)
+        });
+        let board = renderer.find(|d| d.layer == RenderLayer::Board);
+        assert!(!board.is_empty());
+        assert!(board.iter().all(|d| d.clip.is_some()));
+    }
+
+    #[test]
+    fn pads_are_drawn_on_their_tiles() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        let renderer = render(&mut game);
+
+        let pads = game.world
+            .with_components::<(Pad, PadTeam, TilePosition)>()
+            .map(|(_, team, tile)| (*team, (tile.0, tile.1)))
+            .collect::<Vec<_>>();
+        assert!(!pads.is_empty());
+        for (team, tile) in pads {
+            let color = game.palette.team_color(team);
+            renderer.assert_tile_count(&game.grid, tile, 1, "pad ring", |d| {
+                d.command.is_ring() && d.command.has_color(color)
+                    && d.layer == RenderLayer::Markers
+            });
+        }
+    }
+
+    #[test]
+    fn walls_are_drawn_in_the_wall_layer() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        game.insert_wall((4, 4)).unwrap();
+        let renderer = render(&mut game);
+
+        renderer.assert_tile_count(&game.grid, (4, 4), 1, "wall", |d| {
+            d.layer == RenderLayer::Walls
+        });
+    }
 }
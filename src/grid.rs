@@ -1,24 +1,64 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::f32;
 
 use embla::math::Vec2;
 use embla::util::astar::astar;
 
+use flow_field::FlowField;
+use grid_shape::{GridShape, HexOrientation, HexShape, SquareShape};
+use movement::{Heuristic, Movement};
+use terrain::{Terrain, MUD_COST};
+
+/// A tile paired with the direction a blob is sliding in on ice, since that decides where it can
+/// go next.
+pub type PathNode = ((i32, i32), (i32, i32));
+
+/// The two tiles linked by a teleporter.
+pub type Teleporter = ((i32, i32), (i32, i32));
+
 pub struct Grid {
     width: i32,
     height: i32,
-    cell_width: i32,
-    cell_height: i32,
+    shape: Box<GridShape>,
     occupied: HashSet<(i32, i32)>,
+    terrain: HashMap<(i32, i32), Terrain>,
+    teleporters: Vec<Teleporter>,
+    movement: Movement,
+    // offsets of the tiles sharing an edge with a tile
+    adjacent: Vec<(i32, i32)>,
+    tile_capacity: Option<usize>,
+    // flow fields by goal, cleared whenever paths could change
+    flow_fields: RefCell<HashMap<(i32, i32), FlowField>>,
 }
 
 impl Grid {
     pub fn new(width: i32, height: i32, cell_width: i32, cell_height: i32) -> Grid {
+        let shape = SquareShape {
+            cell_width: cell_width as f32,
+            cell_height: cell_height as f32,
+        };
+        Grid::with_shape(width, height, Box::new(shape))
+    }
+
+    /// A board of hexagons with `size` from their center to their corners. The tiles form a
+    /// rhombus in axial coordinates.
+    pub fn hex(width: i32, height: i32, size: f32, orientation: HexOrientation) -> Grid {
+        Grid::with_shape(width, height, Box::new(HexShape { size, orientation }))
+    }
+
+    pub fn with_shape(width: i32, height: i32, shape: Box<GridShape>) -> Grid {
         Grid {
             width,
             height,
-            cell_width,
-            cell_height,
+            movement: shape.movement(),
+            adjacent: shape.movement().neighbors,
+            shape,
             occupied: HashSet::new(),
+            terrain: HashMap::new(),
+            teleporters: Vec::new(),
+            tile_capacity: None,
+            flow_fields: RefCell::new(HashMap::new()),
         }
     }
 
@@ -30,38 +70,80 @@ impl Grid {
         self.height
     }
 
-    pub fn cell_width(&self) -> i32 {
-        self.cell_width
+    /// Width of the bounding box of a cell.
+    pub fn cell_width(&self) -> f32 {
+        self.shape.cell_size().0
+    }
+
+    /// Height of the bounding box of a cell.
+    pub fn cell_height(&self) -> f32 {
+        self.shape.cell_size().1
+    }
+
+    pub fn cell_center(&self, (x, y): (i32, i32)) -> Vec2 {
+        self.shape.cell_center((x, y))
     }
 
-    pub fn cell_height(&self) -> i32 {
-        self.cell_height
+    pub fn cell_corners(&self, (x, y): (i32, i32)) -> Vec<Vec2> {
+        self.shape.cell_corners((x, y))
     }
 
-    pub fn cell_rect(&self, x: i32, y: i32) -> (i32, i32, i32, i32) {
-        let (x, y) = (x * self.cell_width, y * self.cell_height);
-        (x, y, x + self.cell_width, y + self.cell_height)
+    /// Corners of a cell with its edges moved `inset` towards the center.
+    pub fn cell_outline(&self, tile: (i32, i32), inset: f32) -> Vec<Vec2> {
+        let center = self.cell_center(tile);
+        let corners = self.cell_corners(tile);
+        // corners of a regular polygon move further than its edges
+        let scale = inset / (f32::consts::PI / corners.len() as f32).cos();
+        corners
+            .into_iter()
+            .map(|corner| {
+                let offset = corner - center;
+                corner - offset / offset.mag() * scale
+            })
+            .collect()
+    }
+
+    /// Same as `cell_outline`, but relative to the center of the cell.
+    pub fn centered_outline(&self, tile: (i32, i32), inset: f32) -> Vec<Vec2> {
+        let center = self.cell_center(tile);
+        self.cell_outline(tile, inset)
+            .into_iter()
+            .map(|corner| corner - center)
+            .collect()
+    }
+
+    /// Lower left and upper right corners of the box around the whole board.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let mut min = Vec2::new(f32::MAX, f32::MAX);
+        let mut max = Vec2::new(f32::MIN, f32::MIN);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                for corner in self.cell_corners((x, y)) {
+                    min = Vec2::new(min.0.min(corner.0), min.1.min(corner.1));
+                    max = Vec2::new(max.0.max(corner.0), max.1.max(corner.1));
+                }
+            }
+        }
+        (min, max)
     }
 
     pub fn tile_at(&self, pos: Vec2) -> Option<(i32, i32)> {
-        let mouse_tile = (
-            (pos.0 / self.cell_width as f32).floor() as i32,
-            (pos.1 / self.cell_height as f32).floor() as i32,
-        );
-        if mouse_tile.0 >= 0 && mouse_tile.0 < self.width && mouse_tile.1 >= 0
-            && mouse_tile.1 < self.height
-        {
-            Some(mouse_tile)
+        let tile = self.shape.tile_at(pos);
+        if self.contains(tile) {
+            Some(tile)
         } else {
             None
         }
     }
 
     pub fn set_occupied(&mut self, tile: (i32, i32), occupied: bool) {
-        if occupied {
-            self.occupied.insert(tile);
+        let changed = if occupied {
+            self.occupied.insert(tile)
         } else {
-            self.occupied.remove(&tile);
+            self.occupied.remove(&tile)
+        };
+        if changed {
+            self.flow_fields.get_mut().clear();
         }
     }
 
@@ -69,26 +151,311 @@ impl Grid {
         self.occupied.contains(tile)
     }
 
-    pub fn find_path<'a>(&'a self, start: (i32, i32), end: (i32, i32)) -> Option<Vec<(i32, i32)>> {
-        astar(
-            start,
-            end,
-            |n| {
-                Box::new([(1, 0), (0, 1), (-1, 0), (0, -1)].into_iter().filter_map(
-                    move |direction| {
-                        let adjacent = (n.0 + direction.0, n.1 + direction.1);
-                        if adjacent.0 >= 0 && adjacent.0 < self.width() && adjacent.1 >= 0
-                            && adjacent.1 < self.height()
-                            && !self.occupied(&adjacent)
-                        {
-                            Some((adjacent, 1))
-                        } else {
-                            None
-                        }
-                    },
-                ))
-            },
-            |from, to| (to.0 - from.0).abs() + (to.1 - from.1).abs(),
-        )
+    pub fn set_terrain(&mut self, tile: (i32, i32), terrain: Option<Terrain>) {
+        match terrain {
+            Some(terrain) => self.terrain.insert(tile, terrain),
+            None => self.terrain.remove(&tile),
+        };
+        self.flow_fields.get_mut().clear();
+    }
+
+    pub fn terrain(&self, tile: &(i32, i32)) -> Option<Terrain> {
+        self.terrain.get(tile).cloned()
+    }
+
+    /// Links two tiles so blobs can jump between them for free.
+    pub fn add_teleporter(&mut self, a: (i32, i32), b: (i32, i32)) {
+        self.teleporters.push((a, b));
+        self.flow_fields.get_mut().clear();
+    }
+
+    pub fn teleporters(&self) -> &[Teleporter] {
+        &self.teleporters
+    }
+
+    /// The tile linked to `tile` if it is a teleporter.
+    pub fn teleporter(&self, tile: &(i32, i32)) -> Option<(i32, i32)> {
+        self.teleporters.iter().find_map(|&(a, b)| {
+            if a == *tile {
+                Some(b)
+            } else if b == *tile {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Tiles sharing an edge with `tile`, whichever way blobs are allowed to move.
+    pub fn neighbors(&self, tile: (i32, i32)) -> Vec<(i32, i32)> {
+        self.adjacent
+            .iter()
+            .map(|&(dx, dy)| (tile.0 + dx, tile.1 + dy))
+            .collect()
+    }
+
+    pub fn adjacent(&self, a: (i32, i32), b: (i32, i32)) -> bool {
+        self.adjacent.contains(&(b.0 - a.0, b.1 - a.1))
+    }
+
+    #[cfg(test)]
+    pub fn movement(&self) -> &Movement {
+        &self.movement
+    }
+
+    pub fn set_movement(&mut self, movement: Movement) {
+        self.movement = movement;
+        self.flow_fields.get_mut().clear();
+    }
+
+    fn contains(&self, tile: (i32, i32)) -> bool {
+        tile.0 >= 0 && tile.0 < self.width && tile.1 >= 0 && tile.1 < self.height
+    }
+
+    pub fn blocked(&self, tile: (i32, i32)) -> bool {
+        !self.contains(tile) || self.occupied(&tile)
+    }
+
+    /// Whether a single step in `direction` from `tile` is allowed.
+    fn can_step(&self, tile: (i32, i32), direction: (i32, i32)) -> bool {
+        let next = (tile.0 + direction.0, tile.1 + direction.1);
+        if self.blocked(next) || self.movement.blocks_corner(tile, direction, |t| self.blocked(t)) {
+            return false;
+        }
+        if let Some(Terrain::OneWay(d)) = self.terrain(&tile) {
+            if direction != d.offset() {
+                return false;
+            }
+        }
+        if let Some(Terrain::OneWay(d)) = self.terrain(&next) {
+            let offset = d.offset();
+            if direction == (-offset.0, -offset.1) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Tiles reachable in one step from `node` and their cost, when heading for `end`.
+    pub fn steps(&self, (tile, sliding): PathNode, end: (i32, i32)) -> Vec<(PathNode, i32)> {
+        let movement = &self.movement;
+        let forced = match self.terrain(&tile) {
+            Some(Terrain::Conveyor(d)) => Some(d.offset()),
+            Some(Terrain::Ice) if sliding != (0, 0) => Some(sliding),
+            _ => None,
+        };
+        let directions = match forced {
+            Some(d) if self.can_step(tile, d) => vec![d],
+            _ => movement.neighbors.clone(),
+        };
+
+        let mut steps = Vec::new();
+        // jumping to the other end of a teleporter is free
+        if forced.is_none() {
+            if let Some(other) = self.teleporter(&tile) {
+                if !self.blocked(other) {
+                    steps.push(((other, (0, 0)), 0));
+                }
+            }
+        }
+        for d in directions {
+            if !self.can_step(tile, d) {
+                continue;
+            }
+            let next = (tile.0 + d.0, tile.1 + d.1);
+            let terrain = self.terrain(&next);
+            let cost = match terrain {
+                Some(Terrain::Mud) => movement.step_cost(d) + MUD_COST,
+                _ => movement.step_cost(d),
+            };
+            let sliding = if terrain == Some(Terrain::Ice) && next != end {
+                d
+            } else {
+                (0, 0)
+            };
+            steps.push(((next, sliding), cost));
+        }
+        steps
+    }
+
+    /// How many blobs fit on a tile before others have to queue, `None` for no limit.
+    pub fn tile_capacity(&self) -> Option<usize> {
+        self.tile_capacity
+    }
+
+    pub fn set_tile_capacity(&mut self, capacity: Option<usize>) {
+        self.tile_capacity = capacity;
+    }
+
+    /// Same as `find_path`, but reads the path from a flow field towards `end` that is shared
+    /// between calls until the grid changes.
+    pub fn flow_path(&self, start: (i32, i32), end: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        self.flow_path_from((start, (0, 0)), end)
+    }
+
+    /// Same as `flow_path`, but from a tile paired with the direction a blob is sliding in.
+    pub fn flow_path_from(&self, start: PathNode, end: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let mut fields = self.flow_fields.borrow_mut();
+        let field = fields
+            .entry(end)
+            .or_insert_with(|| FlowField::new(self, end));
+        field.path(start)
+    }
+
+    /// Lower bound of the cost from `from` to `to`, which may go through any number of
+    /// teleporters. Any such path walks to some teleporter first and walks away from some
+    /// teleporter last, with the jumps in between being free.
+    fn estimate(&self, heuristic: Heuristic, from: (i32, i32), to: (i32, i32)) -> i32 {
+        let movement = &self.movement;
+        let direct = movement.estimate(heuristic, from, to);
+        let ends = self.teleporters.iter().flat_map(|&(a, b)| vec![a, b]);
+        let to_teleporter = ends.clone().map(|end| movement.estimate(heuristic, from, end)).min();
+        let from_teleporter = ends.map(|end| movement.estimate(heuristic, end, to)).min();
+        match (to_teleporter, from_teleporter) {
+            (Some(a), Some(b)) => direct.min(a + b),
+            _ => direct,
+        }
+    }
+
+    pub fn find_path(&self, start: (i32, i32), end: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        let heuristic = self.movement.heuristic();
+        let path = astar(
+            (start, (0, 0)),
+            (end, (0, 0)),
+            |node| Box::new(self.steps(node, end).into_iter()),
+            |(from, _), (to, _)| self.estimate(heuristic, from, to),
+        )?;
+
+        Some(path.into_iter().map(|(tile, _)| tile).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, HashMap};
+
+    use super::Grid;
+    use movement::Movement;
+
+    const SIZE: i32 = 12;
+
+    // a board with scattered walls, leaving the corners open
+    fn board(movement: Movement) -> Grid {
+        let mut grid = Grid::new(SIZE, SIZE, 10, 10);
+        grid.set_movement(movement);
+        for x in 0..SIZE {
+            for y in 0..SIZE {
+                if (x * 7 + y * 3) % 5 == 0 && (x + y) % SIZE != 0 {
+                    grid.set_occupied((x, y), true);
+                }
+            }
+        }
+        grid
+    }
+
+    // breadth first search from `start`, ordered by cost so steps of different lengths work too
+    fn search(grid: &Grid, start: (i32, i32)) -> HashMap<(i32, i32), i32> {
+        let movement = grid.movement();
+        let mut costs = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        costs.insert(start, 0);
+        queue.push((Reverse(0), start));
+        while let Some((Reverse(cost), tile)) = queue.pop() {
+            if cost > costs[&tile] {
+                continue;
+            }
+            for &d in &movement.neighbors {
+                let next = (tile.0 + d.0, tile.1 + d.1);
+                if grid.blocked(next) || movement.blocks_corner(tile, d, |t| grid.blocked(t)) {
+                    continue;
+                }
+                let total = cost + movement.step_cost(d);
+                if costs.get(&next).is_none_or(|&old| total < old) {
+                    costs.insert(next, total);
+                    queue.push((Reverse(total), next));
+                }
+            }
+        }
+        costs
+    }
+
+    fn path_cost(grid: &Grid, path: &[(i32, i32)]) -> i32 {
+        path.windows(2)
+            .map(|step| {
+                if grid.teleporter(&step[0]) == Some(step[1]) {
+                    return 0;
+                }
+                let d = (step[1].0 - step[0].0, step[1].1 - step[0].1);
+                assert!(grid.movement().neighbors.contains(&d), "bad step {:?}", step);
+                assert!(!grid.blocked(step[1]), "step into a wall {:?}", step);
+                grid.movement().step_cost(d)
+            })
+            .sum()
+    }
+
+    fn assert_shortest_paths(movement: Movement) {
+        let grid = board(movement);
+        for &start in &[(0, 0), (SIZE - 1, 0), (5, 11)] {
+            let costs = search(&grid, start);
+            for x in 0..SIZE {
+                for y in 0..SIZE {
+                    let end = (x, y);
+                    let path = grid.find_path(start, end);
+                    assert_eq!(path.is_some(), costs.contains_key(&end), "{:?}", end);
+                    if let Some(path) = path {
+                        assert_eq!(path_cost(&grid, &path), costs[&end], "{:?}", end);
+                        let flow_path = grid.flow_path(start, end).unwrap();
+                        assert_eq!(path_cost(&grid, &flow_path), costs[&end], "{:?}", end);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn four_way_paths_are_shortest() {
+        assert_shortest_paths(Movement::four_way());
+    }
+
+    #[test]
+    fn eight_way_paths_are_shortest() {
+        assert_shortest_paths(Movement::eight_way(false));
+        assert_shortest_paths(Movement::eight_way(true));
+    }
+
+    #[test]
+    fn king_paths_are_shortest() {
+        assert_shortest_paths(Movement::king(false));
+    }
+
+    #[test]
+    fn custom_paths_are_shortest() {
+        let knight = vec![(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+        assert_shortest_paths(Movement::custom(knight.clone(), true));
+        assert_shortest_paths(Movement::custom(knight, false));
+    }
+
+    #[test]
+    fn teleporter_chains_are_never_overestimated() {
+        // walking into the first teleporter lands next to the second, and so on down the row
+        let mut grid = Grid::new(30, 3, 10, 10);
+        grid.add_teleporter((1, 1), (10, 1));
+        grid.add_teleporter((11, 1), (20, 1));
+        grid.add_teleporter((21, 1), (28, 1));
+        let heuristic = grid.movement().heuristic();
+        let end = (29, 1);
+
+        for x in 0..grid.width() {
+            for y in 0..grid.height() {
+                let flow_path = grid.flow_path((x, y), end).unwrap();
+                let cost = path_cost(&grid, &flow_path);
+                assert!(grid.estimate(heuristic, (x, y), end) <= cost, "{:?}", (x, y));
+
+                let path = grid.find_path((x, y), end).unwrap();
+                assert_eq!(path_cost(&grid, &path), cost, "{:?}", (x, y));
+            }
+        }
+        assert_eq!(grid.find_path((0, 1), end).unwrap().len(), 8);
     }
 }
@@ -0,0 +1,150 @@
+use embla::math::Vec2;
+
+use movement::Movement;
+
+/// Geometry of the tiles of a grid.
+pub trait GridShape {
+    /// World position of the center of a tile.
+    fn cell_center(&self, tile: (i32, i32)) -> Vec2;
+
+    /// Corners of a tile going counter-clockwise.
+    fn cell_corners(&self, tile: (i32, i32)) -> Vec<Vec2>;
+
+    /// Width and height of the bounding box of a tile.
+    fn cell_size(&self) -> (f32, f32);
+
+    /// The tile containing a world position, which may be outside the board.
+    fn tile_at(&self, pos: Vec2) -> (i32, i32);
+
+    /// Moving to any adjacent tile, with a heuristic matching the tile layout.
+    fn movement(&self) -> Movement;
+}
+
+/// Rectangular tiles in rows and columns.
+pub struct SquareShape {
+    pub cell_width: f32,
+    pub cell_height: f32,
+}
+
+impl GridShape for SquareShape {
+    fn cell_center(&self, (x, y): (i32, i32)) -> Vec2 {
+        Vec2::new(
+            (x as f32 + 0.5) * self.cell_width,
+            (y as f32 + 0.5) * self.cell_height,
+        )
+    }
+
+    fn cell_corners(&self, (x, y): (i32, i32)) -> Vec<Vec2> {
+        let (x0, y0) = (x as f32 * self.cell_width, y as f32 * self.cell_height);
+        let (x1, y1) = (x0 + self.cell_width, y0 + self.cell_height);
+        vec![
+            Vec2::new(x0, y0),
+            Vec2::new(x1, y0),
+            Vec2::new(x1, y1),
+            Vec2::new(x0, y1),
+        ]
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        (self.cell_width, self.cell_height)
+    }
+
+    fn tile_at(&self, pos: Vec2) -> (i32, i32) {
+        (
+            (pos.0 / self.cell_width).floor() as i32,
+            (pos.1 / self.cell_height).floor() as i32,
+        )
+    }
+
+    fn movement(&self) -> Movement {
+        Movement::four_way()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HexOrientation {
+    /// A corner at the top, so rows of tiles run horizontally.
+    PointyTop,
+    /// An edge at the top, so columns of tiles run vertically.
+    FlatTop,
+}
+
+/// Hexagonal tiles addressed by axial coordinates `(q, r)`, where `r` counts rows for pointy top
+/// hexagons and `q` counts columns for flat top ones.
+pub struct HexShape {
+    /// Distance from the center of a tile to its corners.
+    pub size: f32,
+    pub orientation: HexOrientation,
+}
+
+impl HexShape {
+    // angle of the first corner
+    fn corner_angle(&self) -> f32 {
+        match self.orientation {
+            HexOrientation::PointyTop => 30.0f32.to_radians(),
+            HexOrientation::FlatTop => 0.0,
+        }
+    }
+}
+
+impl GridShape for HexShape {
+    fn cell_center(&self, (q, r): (i32, i32)) -> Vec2 {
+        let (q, r) = (q as f32, r as f32);
+        let sqrt3 = 3.0f32.sqrt();
+        match self.orientation {
+            HexOrientation::PointyTop => {
+                Vec2::new(self.size * sqrt3 * (q + r / 2.0), self.size * 1.5 * r)
+            }
+            HexOrientation::FlatTop => {
+                Vec2::new(self.size * 1.5 * q, self.size * sqrt3 * (r + q / 2.0))
+            }
+        }
+    }
+
+    fn cell_corners(&self, tile: (i32, i32)) -> Vec<Vec2> {
+        let center = self.cell_center(tile);
+        (0..6)
+            .map(|i| {
+                let angle = self.corner_angle() + i as f32 * 60.0f32.to_radians();
+                center + Vec2::with_angle(angle) * self.size
+            })
+            .collect()
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        let sqrt3 = 3.0f32.sqrt();
+        match self.orientation {
+            HexOrientation::PointyTop => (self.size * sqrt3, self.size * 2.0),
+            HexOrientation::FlatTop => (self.size * 2.0, self.size * sqrt3),
+        }
+    }
+
+    fn tile_at(&self, pos: Vec2) -> (i32, i32) {
+        let sqrt3 = 3.0f32.sqrt();
+        let (q, r) = match self.orientation {
+            HexOrientation::PointyTop => (
+                (sqrt3 / 3.0 * pos.0 - pos.1 / 3.0) / self.size,
+                (2.0 / 3.0 * pos.1) / self.size,
+            ),
+            HexOrientation::FlatTop => (
+                (2.0 / 3.0 * pos.0) / self.size,
+                (-pos.0 / 3.0 + sqrt3 / 3.0 * pos.1) / self.size,
+            ),
+        };
+
+        // round in cube coordinates, fixing up the component that was rounded the furthest
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        (rq as i32, rr as i32)
+    }
+
+    fn movement(&self) -> Movement {
+        Movement::hex()
+    }
+}
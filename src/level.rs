@@ -0,0 +1,459 @@
+use std::fs;
+use std::path::Path;
+
+use failure::{err_msg, Error};
+
+use chart;
+use components::{HitShape, Note, PadMovement, PadTeam};
+use grid::Grid;
+use grid_shape::HexOrientation;
+use movement::Movement;
+use terrain::{Direction, Terrain};
+
+pub static LEVEL_PATH: &str = "assets/levels/demo.cfg";
+
+type Tile = (i32, i32);
+
+pub struct LevelPad {
+    pub tile: Tile,
+    pub team: PadTeam,
+    pub movement: PadMovement,
+    pub hit_shape: HitShape,
+    pub cooldown: i32,
+}
+
+pub struct LevelSpawn {
+    pub tile: Tile,
+    pub team: PadTeam,
+    pub interval: i32,
+    pub chart: Vec<Note>,
+}
+
+/// Layout of the tiles of a level's board.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LevelShape {
+    /// Tiles of a width and height in world units.
+    Square(i32, i32),
+    /// Hexagons of a size from their center to their corners.
+    Hex(f32, HexOrientation),
+}
+
+/// The board and everything placed on it when a game starts.
+pub struct Level {
+    pub size: (i32, i32),
+    pub shape: LevelShape,
+    pub tile_capacity: Option<usize>,
+    /// Steps blobs can take, the grid's own neighbours if not set.
+    pub movement: Option<Movement>,
+    pub terrain: Vec<(Tile, Terrain)>,
+    pub teleporters: Vec<(Tile, Tile)>,
+    pub walls: Vec<Tile>,
+    pub pads: Vec<LevelPad>,
+    pub spawns: Vec<LevelSpawn>,
+    pub goals: Vec<(Tile, PadTeam)>,
+    pub gates: Vec<(Tile, PadTeam)>,
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, Error> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        Level::parse(&source).map_err(|e| err_msg(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parses a level source, one `name = value` per line.
+    pub fn parse(source: &str) -> Result<Level, Error> {
+        let mut level = Level {
+            size: (0, 0),
+            shape: LevelShape::Square(0, 0),
+            tile_capacity: None,
+            movement: None,
+            terrain: Vec::new(),
+            teleporters: Vec::new(),
+            walls: Vec::new(),
+            pads: Vec::new(),
+            spawns: Vec::new(),
+            goals: Vec::new(),
+            gates: Vec::new(),
+        };
+
+        // tiles placed on each line and the line of each spawn, checked once the size is known
+        let mut placed = Vec::new();
+        let mut spawn_lines = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| err_msg(format!("line {}: expected `name = value`", i + 1)))?
+                .trim();
+            let tiles = level
+                .apply(name, value)
+                .map_err(|e| err_msg(format!("line {}: {}", i + 1, e)))?;
+            placed.extend(tiles.into_iter().map(|tile| (i + 1, tile)));
+            if name == "spawn" {
+                spawn_lines.push(i + 1);
+            }
+        }
+
+        if level.size.0 <= 0 || level.size.1 <= 0 {
+            return Err(err_msg("expected a board `size`"));
+        }
+        let has_shape = match level.shape {
+            LevelShape::Square(w, h) => w > 0 && h > 0,
+            LevelShape::Hex(size, _) => size > 0.0,
+        };
+        if !has_shape {
+            return Err(err_msg("expected a tile `shape`"));
+        }
+
+        let (width, height) = level.size;
+        for &(line, (x, y)) in &placed {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                return Err(err_msg(format!("line {}: {} {} is off the board", line, x, y)));
+            }
+        }
+        for (spawn, line) in level.spawns.iter().zip(spawn_lines) {
+            if !level.goals.iter().any(|&(_, team)| team == spawn.team) {
+                let team = format!("{:?}", spawn.team).to_lowercase();
+                return Err(err_msg(format!("line {}: no goal for the {} team", line, team)));
+            }
+        }
+
+        Ok(level)
+    }
+
+    /// Applies a line to the level, returns the tiles it placed things on.
+    fn apply(&mut self, name: &str, value: &str) -> Result<Vec<Tile>, Error> {
+        let mut tokens = value.split_whitespace();
+        let mut placed = Vec::new();
+        match name {
+            "size" => self.size = pair(&mut tokens)?,
+            "shape" => {
+                self.shape = match tokens.next() {
+                    Some("square") => {
+                        let (w, h) = pair(&mut tokens)?;
+                        LevelShape::Square(w, h)
+                    }
+                    Some("hex") => {
+                        let size = number(&mut tokens)? as f32;
+                        let orientation = match tokens.next() {
+                            Some("pointy_top") => HexOrientation::PointyTop,
+                            Some("flat_top") => HexOrientation::FlatTop,
+                            _ => return Err(err_msg("expected pointy_top or flat_top")),
+                        };
+                        LevelShape::Hex(size, orientation)
+                    }
+                    _ => return Err(err_msg("expected square or hex")),
+                }
+            }
+            "tile_capacity" => {
+                let capacity = number(&mut tokens)?;
+                if capacity < 1 {
+                    return Err(err_msg("expected a capacity of at least one blob"));
+                }
+                self.tile_capacity = Some(capacity as usize);
+            }
+            "movement" => {
+                let movement = match tokens.next() {
+                    Some("four_way") => Movement::four_way(),
+                    Some("eight_way") => Movement::eight_way(cut_corners(&mut tokens)?),
+                    Some("king") => Movement::king(cut_corners(&mut tokens)?),
+                    Some("hex") => Movement::hex(),
+                    Some("custom") => {
+                        let uniform_cost = match tokens.next() {
+                            Some("uniform") => true,
+                            Some("scaled") => false,
+                            _ => return Err(err_msg("expected uniform or scaled step costs")),
+                        };
+                        let mut neighbors = Vec::new();
+                        while let Some(dx) = tokens.next() {
+                            let step = (dx.parse::<i32>()?, number(&mut tokens)?);
+                            if step == (0, 0) {
+                                return Err(err_msg("expected steps that leave the tile"));
+                            }
+                            neighbors.push(step);
+                        }
+                        if neighbors.is_empty() {
+                            return Err(err_msg("expected steps `dx dy`"));
+                        }
+                        Movement::custom(neighbors, uniform_cost)
+                    }
+                    _ => {
+                        return Err(err_msg(
+                            "expected one of four_way, eight_way, king, hex or custom",
+                        ))
+                    }
+                };
+                self.movement = Some(movement);
+            }
+            "terrain" => {
+                let tile = pair(&mut tokens)?;
+                let terrain = match tokens.next() {
+                    Some("mud") => Terrain::Mud,
+                    Some("ice") => Terrain::Ice,
+                    Some("conveyor") => Terrain::Conveyor(direction(&mut tokens)?),
+                    Some("one_way") => Terrain::OneWay(direction(&mut tokens)?),
+                    _ => return Err(err_msg("expected one of mud, ice, conveyor or one_way")),
+                };
+                placed.push(tile);
+                self.terrain.push((tile, terrain));
+            }
+            "teleporter" => {
+                let a = pair(&mut tokens)?;
+                let b = pair(&mut tokens)?;
+                placed.extend(&[a, b]);
+                self.teleporters.push((a, b));
+            }
+            "wall" => {
+                let tile = pair(&mut tokens)?;
+                placed.push(tile);
+                self.walls.push(tile);
+            }
+            "pad" => {
+                let tile = pair(&mut tokens)?;
+                let team = team(&mut tokens)?;
+                let movement = match tokens.next() {
+                    Some("fixed") => PadMovement::Fixed,
+                    Some("row") => PadMovement::Row,
+                    Some("free") => PadMovement::Free,
+                    _ => return Err(err_msg("expected one of fixed, row or free")),
+                };
+                let hit_shape = match tokens.next() {
+                    Some("tile") => HitShape::Tile,
+                    Some("plus") => HitShape::Plus,
+                    Some("square") => HitShape::Square,
+                    Some("row") => HitShape::Row,
+                    Some("column") => HitShape::Column,
+                    Some("line") => HitShape::Line(direction(&mut tokens)?, number(&mut tokens)?),
+                    _ => {
+                        return Err(err_msg(
+                            "expected one of tile, plus, square, row, column or line",
+                        ))
+                    }
+                };
+                let cooldown = number(&mut tokens)?;
+                self.pads.push(LevelPad {
+                    tile,
+                    team,
+                    movement,
+                    hit_shape,
+                    cooldown,
+                });
+            }
+            "spawn" => {
+                let tile = pair(&mut tokens)?;
+                let team = team(&mut tokens)?;
+                let interval = number(&mut tokens)?;
+                if interval < 1 {
+                    return Err(err_msg("expected an interval of at least one beat"));
+                }
+                let chart = match tokens.next() {
+                    Some(path) => chart::load_chart(path)?,
+                    None => Vec::new(),
+                };
+                placed.push(tile);
+                self.spawns.push(LevelSpawn {
+                    tile,
+                    team,
+                    interval,
+                    chart,
+                });
+            }
+            "goal" => {
+                let tile = pair(&mut tokens)?;
+                placed.push(tile);
+                self.goals.push((tile, team(&mut tokens)?));
+            }
+            "gate" => {
+                let tile = pair(&mut tokens)?;
+                placed.push(tile);
+                self.gates.push((tile, team(&mut tokens)?));
+            }
+            _ => return Err(err_msg(format!("unknown value `{}`", name))),
+        }
+
+        match tokens.next() {
+            Some(token) => Err(err_msg(format!("unexpected `{}`", token))),
+            None => Ok(placed),
+        }
+    }
+
+    /// An empty board of the level's size with its terrain and teleporters.
+    pub fn grid(&self) -> Grid {
+        let (width, height) = self.size;
+        let mut grid = match self.shape {
+            LevelShape::Square(w, h) => Grid::new(width, height, w, h),
+            LevelShape::Hex(size, orientation) => Grid::hex(width, height, size, orientation),
+        };
+        if let Some(ref movement) = self.movement {
+            grid.set_movement(movement.clone());
+        }
+        for &(tile, terrain) in &self.terrain {
+            grid.set_terrain(tile, Some(terrain));
+        }
+        for &(a, b) in &self.teleporters {
+            grid.add_teleporter(a, b);
+        }
+        grid.set_tile_capacity(self.tile_capacity);
+        grid
+    }
+}
+
+fn number<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Result<i32, Error> {
+    Ok(tokens
+        .next()
+        .ok_or_else(|| err_msg("expected a number"))?
+        .parse::<i32>()?)
+}
+
+fn pair<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Result<(i32, i32), Error> {
+    Ok((number(tokens)?, number(tokens)?))
+}
+
+// diagonal steps only squeeze past blocked corners if the line ends in `cut_corners`
+fn cut_corners<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Result<bool, Error> {
+    match tokens.next() {
+        None => Ok(false),
+        Some("cut_corners") => Ok(true),
+        Some(token) => Err(err_msg(format!("unexpected `{}`", token))),
+    }
+}
+
+fn team<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Result<PadTeam, Error> {
+    tokens
+        .next()
+        .and_then(PadTeam::from_name)
+        .ok_or_else(|| err_msg("expected one of blue, red, green or yellow"))
+}
+
+fn direction<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Result<Direction, Error> {
+    match tokens.next() {
+        Some("up") => Ok(Direction::Up),
+        Some("down") => Ok(Direction::Down),
+        Some("left") => Ok(Direction::Left),
+        Some("right") => Ok(Direction::Right),
+        _ => Err(err_msg("expected one of up, down, left or right")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, LEVEL_PATH};
+    use components::{HitShape, PadTeam};
+    use terrain::{Direction, Terrain};
+
+    static HEX_LEVEL_PATH: &str = "assets/levels/hex.cfg";
+
+    #[test]
+    fn demo_level_loads() {
+        let level = Level::load(LEVEL_PATH).unwrap();
+        assert_eq!(level.pads.len(), level.goals.len());
+    }
+
+    #[test]
+    fn placements() {
+        let level = Level::parse(
+            "size = 4 5\n\
+             shape = square 10 10\n\
+             terrain = 1 2 conveyor left # pushes\n\
+             teleporter = 0 0 3 4\n\
+             pad = 1 1 red row line up 2 1\n\
+             gate = 2 2 green\n",
+        ).unwrap();
+        assert_eq!(level.size, (4, 5));
+        assert_eq!(level.terrain, vec![((1, 2), Terrain::Conveyor(Direction::Left))]);
+        assert_eq!(level.teleporters, vec![((0, 0), (3, 4))]);
+        assert_eq!(level.pads[0].hit_shape, HitShape::Line(Direction::Up, 2));
+        assert!(level.gates == vec![((2, 2), PadTeam::Green)]);
+
+        let grid = level.grid();
+        assert_eq!(grid.teleporter(&(3, 4)), Some((0, 0)));
+    }
+
+    #[test]
+    fn movement() {
+        let board = "size = 4 5\nshape = square 10 10\n";
+        let level = Level::parse(&format!("{}movement = king cut_corners", board)).unwrap();
+        let movement = level.grid().movement().clone();
+        assert!(movement.uniform_cost && movement.cut_corners);
+        assert_eq!(movement.neighbors.len(), 8);
+
+        let level = Level::parse(&format!("{}movement = custom uniform 1 2 2 1", board)).unwrap();
+        assert_eq!(level.grid().movement().neighbors, vec![(1, 2), (2, 1)]);
+
+        assert_eq!(Level::parse(board).unwrap().grid().movement().neighbors.len(), 4);
+    }
+
+    #[test]
+    fn hex_boards() {
+        let level = Level::parse("size = 4 5\nshape = hex 10 pointy_top").unwrap();
+        assert_eq!(level.grid().movement().neighbors.len(), 6);
+        Level::load(HEX_LEVEL_PATH).unwrap();
+    }
+
+    #[test]
+    fn bad_lines_are_rejected() {
+        let board = "size = 4 5\nshape = square 10 10\n";
+        assert!(Level::parse("shape = square 10 10").is_err());
+        assert!(Level::parse("size = 4 5\nshape = hex 10 sideways").is_err());
+        assert!(Level::parse(&format!("{}terrain = 1 1 lava", board)).is_err());
+        assert!(Level::parse(&format!("{}gate = 1 1 purple", board)).is_err());
+        assert!(Level::parse(&format!("{}wall = 1 1 1", board)).is_err());
+        assert!(Level::parse(&format!("{}spawn = 1 1 red 0", board)).is_err());
+        assert!(Level::parse(&format!("{}movement = eight_way 1", board)).is_err());
+        assert!(Level::parse(&format!("{}movement = custom uniform 1", board)).is_err());
+        assert!(Level::parse(&format!("{}movement = custom uniform 1 0 0 0", board)).is_err());
+    }
+
+    fn error(source: &str) -> String {
+        match Level::parse(source) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn placements_off_the_board_are_rejected() {
+        let board = "size = 4 5\nshape = square 10 10\n";
+        assert_eq!(error(&format!("{}wall = 4 0", board)), "line 3: 4 0 is off the board");
+        assert_eq!(error(&format!("{}terrain = 0 5 mud", board)), "line 3: 0 5 is off the board");
+        assert_eq!(
+            error(&format!("{}teleporter = 0 0 -1 2", board)),
+            "line 3: -1 2 is off the board"
+        );
+        assert_eq!(error(&format!("{}gate = 9 9 red", board)), "line 3: 9 9 is off the board");
+        assert_eq!(
+            error(&format!("{}goal = 1 0 red\nspawn = 1 -1 red 1", board)),
+            "line 4: 1 -1 is off the board"
+        );
+        assert_eq!(error(&format!("{}goal = 4 4 red", board)), "line 3: 4 4 is off the board");
+
+        // placements can come before the size
+        assert_eq!(
+            error("wall = 7 0\nsize = 4 5\nshape = square 10 10"),
+            "line 1: 7 0 is off the board"
+        );
+    }
+
+    #[test]
+    fn spawns_need_a_goal_for_their_team() {
+        let board = "size = 4 5\nshape = square 10 10\n";
+        let source = format!("{}goal = 1 0 red\nspawn = 1 4 red 1\nspawn = 2 4 blue 1", board);
+        assert_eq!(error(&source), "line 5: no goal for the blue team");
+    }
+
+    #[test]
+    fn tiles_hold_at_least_one_blob() {
+        let board = "size = 4 5\nshape = square 10 10\n";
+        assert_eq!(
+            error(&format!("{}tile_capacity = 0", board)),
+            "line 3: expected a capacity of at least one blob"
+        );
+    }
+}
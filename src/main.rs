@@ -1,19 +1,80 @@
 extern crate embla;
 extern crate failure;
+extern crate png;
 
 mod application;
+mod camera;
+mod chart;
 mod components;
+mod controls;
+mod debug_overlay;
+mod flow_field;
+mod font;
 mod game;
 mod grid;
+mod grid_shape;
+mod level;
+mod movement;
+mod palette;
+mod recording_renderer;
+mod replay;
 mod render_interface;
 mod renderer;
+mod screen_shake;
+mod software_renderer;
+mod sprites;
 mod systems;
+mod terrain;
+mod theme;
+mod ui;
 
 use application::Application;
+use level::{Level, LEVEL_PATH};
+use replay::ReplayRecorder;
+
+const SNAPSHOT_SIZE: (i32, i32) = (640, 480);
+const BENCH_GRID_SIZE: i32 = 100;
+const BENCH_BLOBS: usize = 5000;
 
 pub fn main() {
+    // `game --snapshot <path>` renders a frame without opening a window
+    let mut args = std::env::args().collect::<Vec<_>>();
+    // `--level <path>` plays another level with any of the other options
+    let level_path = match args.iter().position(|arg| arg == "--level") {
+        Some(i) if i + 1 < args.len() => args.drain(i..i + 2).nth(1).unwrap(),
+        _ => LEVEL_PATH.to_string(),
+    };
+    let level = Level::load(&level_path).unwrap();
+
+    if args.len() == 3 && args[1] == "--snapshot" {
+        application::render_snapshot(&args[2], SNAPSHOT_SIZE, &level).unwrap();
+        return;
+    }
+    // `game --dump-draws` lists the draw calls of a frame
+    if args.len() == 2 && args[1] == "--dump-draws" {
+        application::dump_draws(SNAPSHOT_SIZE, &level).unwrap();
+        return;
+    }
+
+    // `game --capture <replay> <dir>` renders a replay to numbered PNG frames
+    if args.len() == 4 && args[1] == "--capture" {
+        application::capture(&args[2], &args[3], SNAPSHOT_SIZE, &level).unwrap();
+        return;
+    }
+    // `game --bench-pathfinding` compares A* against flow fields on a large grid
+    if args.len() == 2 && args[1] == "--bench-pathfinding" {
+        application::bench_pathfinding(BENCH_GRID_SIZE, BENCH_BLOBS);
+        return;
+    }
+    // `game --record <replay>` saves the session for capturing later
+    let recorder = if args.len() == 3 && args[1] == "--record" {
+        Some(ReplayRecorder::create(&args[2]).unwrap())
+    } else {
+        None
+    };
+
     embla::run(|| {
-        let mut application = Application::new().unwrap();
+        let mut application = Application::new(recorder, level).unwrap();
         move |dt, input| {
             application.update(dt, input)?;
 
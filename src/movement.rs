@@ -0,0 +1,147 @@
+/// Cost of a single straight step, diagonal steps cost about 1.4 times as much.
+pub const STEP_COST: i32 = 10;
+
+/// Distance estimate used by A*, matching the neighborhood so it never overestimates.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Heuristic {
+    Manhattan,
+    /// 8-way movement where diagonal steps cost more than straight ones.
+    Octile,
+    /// 8-way movement where every step costs the same.
+    Chebyshev,
+    /// Steps to the six neighbors of a hexagonal tile in axial coordinates.
+    Hex,
+    /// Any other set of steps, scaled to stay below the real cost.
+    Euclidean,
+}
+
+/// Rules for moving between tiles.
+#[derive(Clone)]
+pub struct Movement {
+    /// Offsets of the tiles reachable in one step.
+    pub neighbors: Vec<(i32, i32)>,
+    /// Whether longer steps cost the same as straight ones instead of their length.
+    pub uniform_cost: bool,
+    /// Whether diagonal steps may squeeze past the corner of a blocked tile.
+    pub cut_corners: bool,
+}
+
+impl Movement {
+    pub fn four_way() -> Movement {
+        Movement {
+            neighbors: vec![(1, 0), (0, 1), (-1, 0), (0, -1)],
+            uniform_cost: false,
+            cut_corners: false,
+        }
+    }
+
+    pub fn eight_way(cut_corners: bool) -> Movement {
+        Movement {
+            neighbors: vec![
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+            ],
+            uniform_cost: false,
+            cut_corners,
+        }
+    }
+
+    /// 8-way movement with diagonal steps as cheap as straight ones.
+    pub fn king(cut_corners: bool) -> Movement {
+        Movement {
+            uniform_cost: true,
+            ..Movement::eight_way(cut_corners)
+        }
+    }
+
+    /// Neighbors of a tile on a hexagonal grid in axial coordinates, all equally far away.
+    pub fn hex() -> Movement {
+        Movement {
+            neighbors: vec![(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)],
+            uniform_cost: true,
+            // hexagons have no corners to cut past
+            cut_corners: true,
+        }
+    }
+
+    pub fn custom(neighbors: Vec<(i32, i32)>, uniform_cost: bool) -> Movement {
+        Movement {
+            neighbors,
+            uniform_cost,
+            cut_corners: true,
+        }
+    }
+
+    pub fn step_cost(&self, (dx, dy): (i32, i32)) -> i32 {
+        if self.uniform_cost {
+            STEP_COST
+        } else {
+            (STEP_COST as f32 * ((dx * dx + dy * dy) as f32).sqrt()).round() as i32
+        }
+    }
+
+    pub fn heuristic(&self) -> Heuristic {
+        let mut sorted = self.neighbors.clone();
+        sorted.sort();
+        let mut four = Movement::four_way().neighbors;
+        four.sort();
+        let mut eight = Movement::eight_way(false).neighbors;
+        eight.sort();
+        let mut hex = Movement::hex().neighbors;
+        hex.sort();
+
+        if sorted == four {
+            Heuristic::Manhattan
+        } else if sorted == eight && self.uniform_cost {
+            Heuristic::Chebyshev
+        } else if sorted == eight {
+            Heuristic::Octile
+        } else if sorted == hex && self.uniform_cost {
+            Heuristic::Hex
+        } else {
+            Heuristic::Euclidean
+        }
+    }
+
+    /// Lower bound of the cost of moving from `from` to `to`, `heuristic` is passed in so it
+    /// doesn't have to be worked out for every tile searched.
+    pub fn estimate(&self, heuristic: Heuristic, from: (i32, i32), to: (i32, i32)) -> i32 {
+        let dx = (to.0 - from.0).abs();
+        let dy = (to.1 - from.1).abs();
+        let (long, short) = (dx.max(dy), dx.min(dy));
+        match heuristic {
+            Heuristic::Manhattan => (dx + dy) * STEP_COST,
+            Heuristic::Octile => (long - short) * STEP_COST + short * self.step_cost((1, 1)),
+            Heuristic::Chebyshev => long * STEP_COST,
+            Heuristic::Hex => {
+                let ds = (to.0 + to.1 - from.0 - from.1).abs();
+                (dx + dy + ds) / 2 * STEP_COST
+            }
+            Heuristic::Euclidean => {
+                // the cheapest cost per tile travelled of any step keeps this admissible
+                let rate = self.neighbors
+                    .iter()
+                    .map(|&(x, y)| self.step_cost((x, y)) as f32 / ((x * x + y * y) as f32).sqrt())
+                    .fold(STEP_COST as f32, f32::min);
+                (((dx * dx + dy * dy) as f32).sqrt() * rate).floor() as i32
+            }
+        }
+    }
+
+    /// Whether a diagonal step has to go around the corner of a blocked tile.
+    pub fn blocks_corner<F>(&self, from: (i32, i32), (dx, dy): (i32, i32), blocked: F) -> bool
+    where
+        F: Fn((i32, i32)) -> bool,
+    {
+        if self.cut_corners || dx.abs() != 1 || dy.abs() != 1 {
+            return false;
+        }
+        blocked((from.0 + dx, from.1)) || blocked((from.0, from.1 + dy))
+    }
+}
@@ -0,0 +1,62 @@
+use components::PadTeam;
+
+/// Color sets for the teams, including ones that stay distinguishable with common forms of
+/// color blindness.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Palette {
+    Default,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    HighContrast,
+}
+
+impl Palette {
+    pub fn next(&self) -> Palette {
+        match *self {
+            Palette::Default => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::HighContrast,
+            Palette::HighContrast => Palette::Default,
+        }
+    }
+
+    pub fn team_color(&self, team: PadTeam) -> (f32, f32, f32, f32) {
+        match *self {
+            Palette::Default => match team {
+                PadTeam::Blue => (0.5, 0.5, 1.0, 1.0),
+                PadTeam::Red => (1.0, 0.5, 0.5, 1.0),
+                PadTeam::Green => (0.5, 1.0, 0.5, 1.0),
+                PadTeam::Yellow => (1.0, 1.0, 0.5, 1.0),
+            },
+            // red and green look alike, lean on blue/orange and brightness instead
+            Palette::Deuteranopia => match team {
+                PadTeam::Blue => (0.0, 0.45, 0.7, 1.0),
+                PadTeam::Red => (0.9, 0.6, 0.0, 1.0),
+                PadTeam::Green => (0.35, 0.7, 0.9, 1.0),
+                PadTeam::Yellow => (0.95, 0.9, 0.25, 1.0),
+            },
+            // reds appear dark, keep every color bright
+            Palette::Protanopia => match team {
+                PadTeam::Blue => (0.2, 0.4, 0.95, 1.0),
+                PadTeam::Red => (0.8, 0.6, 0.7, 1.0),
+                PadTeam::Green => (0.6, 0.85, 1.0, 1.0),
+                PadTeam::Yellow => (0.95, 0.9, 0.25, 1.0),
+            },
+            // blue and yellow look alike, lean on red/teal instead
+            Palette::Tritanopia => match team {
+                PadTeam::Blue => (0.0, 0.6, 0.6, 1.0),
+                PadTeam::Red => (0.85, 0.15, 0.15, 1.0),
+                PadTeam::Green => (0.6, 0.95, 0.95, 1.0),
+                PadTeam::Yellow => (1.0, 0.65, 0.8, 1.0),
+            },
+            Palette::HighContrast => match team {
+                PadTeam::Blue => (0.2, 0.5, 1.0, 1.0),
+                PadTeam::Red => (1.0, 0.1, 0.1, 1.0),
+                PadTeam::Green => (0.1, 1.0, 0.1, 1.0),
+                PadTeam::Yellow => (1.0, 1.0, 0.0, 1.0),
+            },
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use embla::graphics::TextureImage;
+use embla::math::Vec2;
+use failure::Error;
+
+use camera::Camera;
+use components::RenderLayer;
+#[cfg(test)]
+use grid::Grid;
+use render_interface::{BlendMode, RenderInterface};
+
+type Color = (f32, f32, f32, f32);
+
+// how far apart two coordinates or colors can be and still count as equal
+#[cfg(test)]
+const EPSILON: f32 = 0.01;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawCommand {
+    Texture {
+        size: (u32, u32),
+        position: (f32, f32),
+        scale: f32,
+        rotation: f32,
+        tint: Color,
+    },
+    Rect {
+        rect: (f32, f32, f32, f32),
+        color: Color,
+    },
+    Polygon {
+        points: Vec<(f32, f32)>,
+        color: Color,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+    },
+    Ring {
+        center: (f32, f32),
+        radius: f32,
+        width: f32,
+        color: Color,
+    },
+    RoundedRect {
+        rect: (f32, f32, f32, f32),
+        radius: f32,
+        color: Color,
+    },
+    Capsule {
+        p1: (f32, f32),
+        p2: (f32, f32),
+        radius: f32,
+        color: Color,
+    },
+    Line {
+        p1: (f32, f32),
+        p2: (f32, f32),
+        width: f32,
+        color: Color,
+    },
+}
+
+// helpers for making assertions about draws in tests
+#[cfg(test)]
+impl DrawCommand {
+    pub fn is_filled_circle(&self) -> bool {
+        matches!(*self, DrawCommand::Circle { .. })
+    }
+
+    pub fn is_ring(&self) -> bool {
+        matches!(*self, DrawCommand::Ring { .. })
+    }
+
+    /// Center of the drawn shape.
+    pub fn center(&self) -> (f32, f32) {
+        let mid = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        match *self {
+            DrawCommand::Texture { position, .. } => position,
+            DrawCommand::Rect { rect, .. } | DrawCommand::RoundedRect { rect, .. } => {
+                mid((rect.0, rect.1), (rect.2, rect.3))
+            }
+            DrawCommand::Polygon { ref points, .. } => {
+                // average of the corners, which is the center of regular polygons
+                let n = points.len() as f32;
+                let sum = points
+                    .iter()
+                    .fold((0.0, 0.0), |sum, p| (sum.0 + p.0, sum.1 + p.1));
+                (sum.0 / n, sum.1 / n)
+            }
+            DrawCommand::Circle { center, .. } | DrawCommand::Ring { center, .. } => center,
+            DrawCommand::Capsule { p1, p2, .. } | DrawCommand::Line { p1, p2, .. } => mid(p1, p2),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match *self {
+            DrawCommand::Texture { tint, .. } => tint,
+            DrawCommand::Rect { color, .. }
+            | DrawCommand::Polygon { color, .. }
+            | DrawCommand::Circle { color, .. }
+            | DrawCommand::Ring { color, .. }
+            | DrawCommand::RoundedRect { color, .. }
+            | DrawCommand::Capsule { color, .. }
+            | DrawCommand::Line { color, .. } => color,
+        }
+    }
+
+    pub fn is_at(&self, point: Vec2) -> bool {
+        let center = self.center();
+        (center.0 - point.0).abs() < EPSILON && (center.1 - point.1).abs() < EPSILON
+    }
+
+    /// Whether the shape is centered on `tile`.
+    pub fn is_on_tile(&self, grid: &Grid, tile: (i32, i32)) -> bool {
+        self.is_at(grid.cell_center(tile))
+    }
+
+    pub fn has_color(&self, color: Color) -> bool {
+        let c = self.color();
+        (c.0 - color.0).abs() < EPSILON && (c.1 - color.1).abs() < EPSILON
+            && (c.2 - color.2).abs() < EPSILON && (c.3 - color.3).abs() < EPSILON
+    }
+}
+
+/// A draw command along with the render state it was issued with.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecordedDraw {
+    pub command: DrawCommand,
+    pub layer: RenderLayer,
+    pub blend_mode: BlendMode,
+    pub clip: Option<(f32, f32, f32, f32)>,
+    pub offset: (f32, f32),
+}
+
+/// Records draw calls instead of drawing them, for checking what gets drawn without depending on
+/// how it is rasterized.
+pub struct RecordingRenderer {
+    screen_size: (i32, i32),
+    draws: Vec<RecordedDraw>,
+    clear_color: Color,
+    layer: RenderLayer,
+    blend_mode: BlendMode,
+    clip: Option<(f32, f32, f32, f32)>,
+    offset: Vec2,
+}
+
+impl RecordingRenderer {
+    pub fn new(screen_size: (i32, i32)) -> RecordingRenderer {
+        RecordingRenderer {
+            screen_size,
+            draws: Vec::new(),
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            layer: RenderLayer::Board,
+            blend_mode: BlendMode::Alpha,
+            clip: None,
+            offset: Vec2::zero(),
+        }
+    }
+
+    pub fn draws(&self) -> &[RecordedDraw] {
+        &self.draws
+    }
+
+    fn record(&mut self, command: DrawCommand) {
+        self.draws.push(RecordedDraw {
+            command,
+            layer: self.layer,
+            blend_mode: self.blend_mode,
+            clip: self.clip,
+            offset: (self.offset.0, self.offset.1),
+        });
+    }
+}
+
+#[cfg(test)]
+impl RecordingRenderer {
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    pub fn find<F>(&self, predicate: F) -> Vec<&RecordedDraw>
+    where
+        F: Fn(&RecordedDraw) -> bool,
+    {
+        self.draws.iter().filter(|d| predicate(d)).collect()
+    }
+
+    /// Panics listing everything that was drawn unless exactly `count` draws match.
+    pub fn assert_count<F>(&self, count: usize, description: &str, predicate: F)
+    where
+        F: Fn(&RecordedDraw) -> bool,
+    {
+        let found = self.find(predicate).len();
+        if found != count {
+            let draws = self.draws
+                .iter()
+                .map(|d| format!("    {:?}", d))
+                .collect::<Vec<_>>()
+                .join("\n");
+            panic!(
+                "expected {} draw(s) of {}, found {} in:\n{}",
+                count, description, found, draws
+            );
+        }
+    }
+
+    /// Like `assert_count`, but only counts draws centered on `tile`.
+    pub fn assert_tile_count<F>(
+        &self,
+        grid: &Grid,
+        tile: (i32, i32),
+        count: usize,
+        description: &str,
+        predicate: F,
+    ) where
+        F: Fn(&RecordedDraw) -> bool,
+    {
+        let description = format!("{} on tile {:?}", description, tile);
+        self.assert_count(count, &description, |d| {
+            d.command.is_on_tile(grid, tile) && predicate(d)
+        });
+    }
+}
+
+impl RenderInterface for RecordingRenderer {
+    fn screen_size(&self) -> (i32, i32) {
+        self.screen_size
+    }
+
+    fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    fn set_camera(&mut self, _camera: &Camera) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn set_offset(&mut self, offset: Vec2) {
+        self.offset = offset;
+    }
+
+    fn set_layer(&mut self, layer: RenderLayer) {
+        self.layer = layer;
+    }
+
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    fn set_clip(&mut self, rect: Option<(f32, f32, f32, f32)>) {
+        self.clip = rect;
+    }
+
+    fn draw_texture(
+        &mut self,
+        texture: &TextureImage,
+        position: Vec2,
+        scale: f32,
+        rotation: f32,
+        tint: Color,
+    ) -> Result<(), Error> {
+        let image = texture.image();
+        self.record(DrawCommand::Texture {
+            size: (image.width, image.height),
+            position: (position.0, position.1),
+            scale,
+            rotation,
+            tint,
+        });
+
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: (f32, f32, f32, f32), color: Color) -> Result<(), Error> {
+        self.record(DrawCommand::Rect { rect, color });
+
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, points: &[Vec2], color: Color) -> Result<(), Error> {
+        self.record(DrawCommand::Polygon {
+            points: points.iter().map(|p| (p.0, p.1)).collect(),
+            color,
+        });
+
+        Ok(())
+    }
+
+    fn draw_circle(&mut self, center: Vec2, radius: f32, color: Color) -> Result<(), Error> {
+        self.record(DrawCommand::Circle {
+            center: (center.0, center.1),
+            radius,
+            color,
+        });
+
+        Ok(())
+    }
+
+    fn draw_ring(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        width: f32,
+        color: Color,
+    ) -> Result<(), Error> {
+        self.record(DrawCommand::Ring {
+            center: (center.0, center.1),
+            radius,
+            width,
+            color,
+        });
+
+        Ok(())
+    }
+
+    fn draw_rounded_rect(
+        &mut self,
+        rect: (f32, f32, f32, f32),
+        radius: f32,
+        color: Color,
+    ) -> Result<(), Error> {
+        self.record(DrawCommand::RoundedRect {
+            rect,
+            radius,
+            color,
+        });
+
+        Ok(())
+    }
+
+    fn draw_capsule(&mut self, p1: Vec2, p2: Vec2, radius: f32, color: Color) -> Result<(), Error> {
+        self.record(DrawCommand::Capsule {
+            p1: (p1.0, p1.1),
+            p2: (p2.0, p2.1),
+            radius,
+            color,
+        });
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, p1: Vec2, p2: Vec2, width: f32, color: Color) -> Result<(), Error> {
+        self.record(DrawCommand::Line {
+            p1: (p1.0, p1.1),
+            p2: (p2.0, p2.1),
+            width,
+            color,
+        });
+
+        Ok(())
+    }
+}
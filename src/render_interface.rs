@@ -4,15 +4,42 @@ use embla::math::Vec2;
 
 use embla::graphics::TextureImage;
 
+use camera::Camera;
+use components::RenderLayer;
+use font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    Alpha,
+    /// Adds to what is already drawn, for glows and particles.
+    Additive,
+}
+
 pub trait RenderInterface {
     fn screen_size(&self) -> (i32, i32);
 
+    fn set_clear_color(&mut self, color: (f32, f32, f32, f32));
+
+    fn set_camera(&mut self, camera: &Camera) -> Result<(), Error>;
+
+    /// Offsets everything drawn after this call, used for screen shake.
+    fn set_offset(&mut self, offset: Vec2);
+
+    /// Sets the layer everything drawn after this call is drawn in.
+    fn set_layer(&mut self, layer: RenderLayer);
+
+    fn set_blend_mode(&mut self, blend_mode: BlendMode);
+
+    /// Restricts drawing to a rect in world coordinates, the camera has to be set first.
+    fn set_clip(&mut self, rect: Option<(f32, f32, f32, f32)>);
+
     fn draw_texture(
         &mut self,
         texture: &TextureImage,
         position: Vec2,
         scale: f32,
         rotation: f32,
+        tint: (f32, f32, f32, f32),
     ) -> Result<(), Error>;
 
     fn draw_rect(
@@ -21,11 +48,38 @@ pub trait RenderInterface {
         color: (f32, f32, f32, f32),
     ) -> Result<(), Error>;
 
+    /// Fills a convex polygon.
+    fn draw_polygon(&mut self, points: &[Vec2], color: (f32, f32, f32, f32)) -> Result<(), Error>;
+
     fn draw_circle(
         &mut self,
         center: Vec2,
         radius: f32,
-        points: i32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error>;
+
+    /// Draws a circle outline of the given width inside `radius`.
+    fn draw_ring(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        width: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error>;
+
+    fn draw_rounded_rect(
+        &mut self,
+        rect: (f32, f32, f32, f32),
+        radius: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error>;
+
+    /// Draws a line with rounded ends, `radius` is half its width.
+    fn draw_capsule(
+        &mut self,
+        p1: Vec2,
+        p2: Vec2,
+        radius: f32,
         color: (f32, f32, f32, f32),
     ) -> Result<(), Error>;
 
@@ -36,4 +90,42 @@ pub trait RenderInterface {
         width: f32,
         color: (f32, f32, f32, f32),
     ) -> Result<(), Error>;
+
+    /// Draws a line of text with its bottom left corner at `position`, `size` is the size of a
+    /// single dot of the font.
+    fn draw_text(
+        &mut self,
+        text: &str,
+        position: Vec2,
+        size: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error> {
+        for (i, c) in text.chars().enumerate() {
+            let x = position.0 + (i as i32 * GLYPH_ADVANCE) as f32 * size;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                let y = position.1 + (GLYPH_HEIGHT - 1 - row as i32) as f32 * size;
+                // one rect for each run of dots in the row
+                let mut column = 0;
+                while column < GLYPH_WIDTH {
+                    if bits & (0b10000 >> column) == 0 {
+                        column += 1;
+                        continue;
+                    }
+                    let start = column;
+                    while column < GLYPH_WIDTH && bits & (0b10000 >> column) != 0 {
+                        column += 1;
+                    }
+                    let rect = (
+                        x + start as f32 * size,
+                        y,
+                        x + column as f32 * size,
+                        y + size,
+                    );
+                    self.draw_rect(rect, color)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -1,5 +1,4 @@
 use failure::Error;
-use std;
 use std::rc::Rc;
 
 use embla::assets::Image;
@@ -8,15 +7,31 @@ use embla::math::Vec2;
 use embla::rendering_api::{Program, Renderer, Texture, TextureFiltering, Uniform, Vertex,
                            VertexAttributeType};
 
-use render_interface::RenderInterface;
+use camera::Camera;
+use components::RenderLayer;
+use render_interface::{BlendMode, RenderInterface};
 
-static VERTEX_SHADER: &'static str = include_str!("../shaders/vertex.glsl");
-static FRAGMENT_SHADER: &'static str = include_str!("../shaders/fragment.glsl");
+static VERTEX_SHADER: &str = include_str!("../shaders/vertex.glsl");
+static FRAGMENT_SHADER: &str = include_str!("../shaders/fragment.glsl");
+static SHAPE_VERTEX_SHADER: &str = include_str!("../shaders/shape_vertex.glsl");
+static SHAPE_FRAGMENT_SHADER: &str = include_str!("../shaders/shape_fragment.glsl");
 
+// extra space around shape quads in pixels, leaves room for the anti-aliased edge
+const SHAPE_MARGIN: f32 = 2.0;
+const TEXTURE_SIZE: (u32, u32) = (4096, 4096);
+
+// a vertex position in pixels and its texel in the atlas page
+type Point = (f32, f32);
+type TexCoord = (u32, u32);
+
+// the fields are only read by the shaders
+#[allow(dead_code)]
 pub struct TexturedVertex {
     pub position: (f32, f32),
     pub tex_coord: (f32, f32),
     pub color: (f32, f32, f32, f32),
+    /// 1 for additive blending, 0 for regular alpha blending.
+    pub additive: f32,
 }
 
 impl Vertex for TexturedVertex {
@@ -25,17 +40,96 @@ impl Vertex for TexturedVertex {
             ("position".into(), 2, VertexAttributeType::Float),
             ("tex_coord".into(), 2, VertexAttributeType::Float),
             ("color".into(), 4, VertexAttributeType::Float),
+            ("additive".into(), 1, VertexAttributeType::Float),
         ]
     }
 }
 
+/// A quad covering a shape that is drawn from its signed distance field.
+#[allow(dead_code)]
+pub struct ShapeVertex {
+    pub position: (f32, f32),
+    /// Position relative to the center of the shape, along the shape's axes.
+    pub local_position: (f32, f32),
+    /// Half width, half height, corner radius and outline width (0 if filled).
+    pub shape: (f32, f32, f32, f32),
+    pub color: (f32, f32, f32, f32),
+    pub additive: f32,
+}
+
+impl Vertex for ShapeVertex {
+    fn attributes() -> Vec<(String, usize, VertexAttributeType)> {
+        vec![
+            ("position".into(), 2, VertexAttributeType::Float),
+            ("local_position".into(), 2, VertexAttributeType::Float),
+            ("shape".into(), 4, VertexAttributeType::Float),
+            ("color".into(), 4, VertexAttributeType::Float),
+            ("additive".into(), 1, VertexAttributeType::Float),
+        ]
+    }
+}
+
+// one texture of the atlas, more pages are added as they fill up
+struct TexturePage<T> {
+    texture: Rc<T>,
+    atlas: TextureAtlas,
+}
+
+// everything that needs its own draw call
+#[derive(Clone, Copy, PartialEq)]
+struct BatchState {
+    layer: RenderLayer,
+    page: usize,
+    // in screen pixels
+    clip: Option<(f32, f32, f32, f32)>,
+}
+
+enum BatchVertices {
+    Textured(Vec<TexturedVertex>),
+    Shapes(Vec<ShapeVertex>),
+}
+
+struct Batch {
+    state: BatchState,
+    vertices: BatchVertices,
+}
+
+impl Batch {
+    // appends the other batch if both can be drawn in the same call
+    fn merge(&mut self, other: Batch) -> Option<Batch> {
+        if self.state != other.state {
+            return Some(other);
+        }
+        match (&mut self.vertices, other.vertices) {
+            (&mut BatchVertices::Textured(ref mut a), BatchVertices::Textured(b)) => a.extend(b),
+            (&mut BatchVertices::Shapes(ref mut a), BatchVertices::Shapes(b)) => a.extend(b),
+            (_, vertices) => {
+                return Some(Batch {
+                    state: other.state,
+                    vertices,
+                })
+            }
+        }
+        None
+    }
+}
+
 pub struct GameRenderer<R: Renderer> {
     program: R::Program,
     vertex_buffer: R::VertexBuffer,
-    vertices: Vec<TexturedVertex>,
-    texture: Rc<R::Texture>,
-    atlas: TextureAtlas,
+    shape_program: R::Program,
+    shape_vertex_buffer: R::VertexBuffer,
+    // submitted batches, sorted by layer before rendering
+    batches: Vec<Batch>,
+    layer: RenderLayer,
+    blend_mode: BlendMode,
+    clip: Option<(f32, f32, f32, f32)>,
+    pages: Vec<TexturePage<R::Texture>>,
     white_texture: [u32; 4],
+    clear_color: (f32, f32, f32, f32),
+    view_scale: f32,
+    view_offset: Vec2,
+    offset: Vec2,
 }
 
 impl<R> GameRenderer<R>
@@ -47,55 +141,237 @@ where
         R: Renderer,
     {
         let mut program = R::create_program(VERTEX_SHADER, FRAGMENT_SHADER)?;
+        let shape_program = R::create_program(SHAPE_VERTEX_SHADER, SHAPE_FRAGMENT_SHADER)?;
 
-        let texture_size = (4096, 4096);
-
-        let texture = Rc::new(R::create_texture(
-            texture_size,
-            Some(TextureFiltering::Nearest),
-        )?);
-
-        let screen_size = R::screen_size();
-        program.set_uniform(
-            "screen_size",
-            Uniform::Vec2((screen_size.0 as f32, screen_size.1 as f32)),
-        );
         program.set_uniform(
             "texture_size",
-            Uniform::Vec2((texture_size.0 as f32, texture_size.1 as f32)),
+            Uniform::Vec2((TEXTURE_SIZE.0 as f32, TEXTURE_SIZE.1 as f32)),
         );
-        program.set_uniform("texture", Uniform::Texture(texture.clone()));
 
-        let mut atlas = TextureAtlas::new(texture_size);
+        let mut page = Self::create_page()?;
         let white_image = TextureImage::new(Rc::new(Image {
             data: vec![255, 255, 255, 255],
             width: 1,
             height: 1,
         }));
-        let white_texture = atlas.add_texture(&white_image)?;
-        texture.set_region(white_image.image(), (white_texture[0], white_texture[1]));
+        let white_texture = page.atlas.add_texture(&white_image)?;
+        page.texture
+            .set_region(white_image.image(), (white_texture[0], white_texture[1]));
 
         Ok(GameRenderer::<R> {
             program,
             vertex_buffer: R::create_vertex_buffer()?,
-            vertices: Vec::new(),
-            texture,
-            atlas,
+            shape_program,
+            shape_vertex_buffer: R::create_vertex_buffer()?,
+            batches: Vec::new(),
+            layer: RenderLayer::Board,
+            blend_mode: BlendMode::Alpha,
+            clip: None,
+            pages: vec![page],
             white_texture,
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            view_scale: 1.0,
+            view_offset: Vec2::zero(),
+            offset: Vec2::zero(),
+        })
+    }
+
+    fn create_page() -> Result<TexturePage<R::Texture>, Error> {
+        Ok(TexturePage {
+            texture: Rc::new(R::create_texture(
+                TEXTURE_SIZE,
+                Some(TextureFiltering::Nearest),
+            )?),
+            atlas: TextureAtlas::new(TEXTURE_SIZE),
         })
     }
 
+    // finds the page and region of a texture, adding it to the atlas if needed
+    fn texture_region(&mut self, texture: &TextureImage) -> Result<(usize, [u32; 4]), Error> {
+        for (i, page) in self.pages.iter().enumerate() {
+            if let Some(region) = page.atlas.get_texture_block(texture) {
+                return Ok((i, region));
+            }
+        }
+
+        let last = self.pages.len() - 1;
+        if let Ok(region) = self.pages[last].atlas.add_texture(texture) {
+            self.pages[last]
+                .texture
+                .set_region(texture.image(), (region[0], region[1]));
+            return Ok((last, region));
+        }
+
+        // the last page is full, start a new one
+        let mut page = Self::create_page()?;
+        let region = page.atlas.add_texture(texture)?;
+        page.texture
+            .set_region(texture.image(), (region[0], region[1]));
+        self.pages.push(page);
+
+        Ok((self.pages.len() - 1, region))
+    }
+
+    fn additive(&self) -> f32 {
+        match self.blend_mode {
+            BlendMode::Alpha => 0.0,
+            BlendMode::Additive => 1.0,
+        }
+    }
+
+    fn push_batch(&mut self, batch: Batch) {
+        if let Some(last) = self.batches.last_mut() {
+            if let Some(batch) = last.merge(batch) {
+                self.batches.push(batch);
+            }
+            return;
+        }
+        self.batches.push(batch);
+    }
+
+    fn push_vertices(
+        &mut self,
+        page: usize,
+        verts: &[(Point, TexCoord)],
+        color: (f32, f32, f32, f32),
+    ) {
+        let additive = self.additive();
+        let vertices = verts
+            .iter()
+            .map(|&(position, tex_coord)| TexturedVertex {
+                position: (position.0 + self.offset.0, position.1 + self.offset.1),
+                tex_coord: (tex_coord.0 as f32, tex_coord.1 as f32),
+                color,
+                additive,
+            })
+            .collect();
+        let state = BatchState {
+            layer: self.layer,
+            page,
+            clip: self.clip,
+        };
+        self.push_batch(Batch {
+            state,
+            vertices: BatchVertices::Textured(vertices),
+        });
+    }
+
+    /// Pushes a quad for a rounded rect centered on `center` with its x axis along `axis`.
+    fn push_shape(
+        &mut self,
+        center: Vec2,
+        axis: Vec2,
+        half_size: (f32, f32),
+        radius: f32,
+        outline: f32,
+        color: (f32, f32, f32, f32),
+    ) {
+        let up = Vec2::new(-axis.1, axis.0);
+        let margin = SHAPE_MARGIN / self.view_scale;
+        let (w, h) = (half_size.0 + margin, half_size.1 + margin);
+        let shape = (half_size.0, half_size.1, radius.min(half_size.0).min(half_size.1), outline);
+        let additive = self.additive();
+
+        let corner = |x: f32, y: f32| {
+            let position = center + axis * x + up * y + self.offset;
+            ShapeVertex {
+                position: (position.0, position.1),
+                local_position: (x, y),
+                shape,
+                color,
+                additive,
+            }
+        };
+        let vertices = vec![
+            corner(-w, -h),
+            corner(-w, h),
+            corner(w, -h),
+            corner(-w, h),
+            corner(w, h),
+            corner(w, -h),
+        ];
+
+        let state = BatchState {
+            layer: self.layer,
+            page: 0,
+            clip: self.clip,
+        };
+        self.push_batch(Batch {
+            state,
+            vertices: BatchVertices::Shapes(vertices),
+        });
+    }
+
     pub fn do_render(&mut self) -> Result<(), Error> {
-        R::clear(Some((0.0, 0.0, 0.0, 1.0)));
+        R::clear(Some(self.clear_color));
+
+        // the window may have been resized since the last frame
+        let screen_size = R::screen_size();
+        for program in [&mut self.program, &mut self.shape_program].iter_mut() {
+            program.set_uniform(
+                "screen_size",
+                Uniform::Vec2((screen_size.0 as f32, screen_size.1 as f32)),
+            );
+            program.set_uniform(
+                "view_scale",
+                Uniform::Vec2((self.view_scale, self.view_scale)),
+            );
+            program.set_uniform(
+                "view_offset",
+                Uniform::Vec2((self.view_offset.0, self.view_offset.1)),
+            );
+        }
 
-        R::render_vertices(&self.vertex_buffer, &self.program, &self.vertices)?;
+        // the sort is stable so batches within a layer keep their submission order, after
+        // sorting neighbouring batches with the same state can share a draw call
+        let mut batches = self.batches.drain(..).collect::<Vec<_>>();
+        batches.sort_by_key(|b| b.state.layer);
+        let mut merged: Vec<Batch> = Vec::new();
+        for batch in batches {
+            let rest = match merged.last_mut() {
+                Some(last) => last.merge(batch),
+                None => Some(batch),
+            };
+            if let Some(batch) = rest {
+                merged.push(batch);
+            }
+        }
 
-        self.vertices.clear();
+        for batch in merged {
+            let clip = batch.state.clip.unwrap_or((
+                0.0,
+                0.0,
+                screen_size.0 as f32,
+                screen_size.1 as f32,
+            ));
+            match batch.vertices {
+                BatchVertices::Textured(vertices) => {
+                    let texture = self.pages[batch.state.page].texture.clone();
+                    self.program
+                        .set_uniform("texture", Uniform::Texture(texture));
+                    set_clip_uniforms(&mut self.program, clip);
+                    R::render_vertices(&self.vertex_buffer, &self.program, &vertices)?;
+                }
+                BatchVertices::Shapes(vertices) => {
+                    set_clip_uniforms(&mut self.shape_program, clip);
+                    R::render_vertices(
+                        &self.shape_vertex_buffer,
+                        &self.shape_program,
+                        &vertices,
+                    )?;
+                }
+            }
+        }
 
         Ok(())
     }
 }
 
+fn set_clip_uniforms<P: Program>(program: &mut P, clip: (f32, f32, f32, f32)) {
+    program.set_uniform("clip_min", Uniform::Vec2((clip.0, clip.1)));
+    program.set_uniform("clip_max", Uniform::Vec2((clip.2, clip.3)));
+}
+
 impl<R> RenderInterface for GameRenderer<R>
 where
     R: Renderer,
@@ -104,22 +380,52 @@ where
         R::screen_size()
     }
 
+    fn set_clear_color(&mut self, color: (f32, f32, f32, f32)) {
+        self.clear_color = color;
+    }
+
+    fn set_camera(&mut self, camera: &Camera) -> Result<(), Error> {
+        self.view_scale = camera.scale();
+        self.view_offset = camera.offset();
+
+        Ok(())
+    }
+
+    fn set_offset(&mut self, offset: Vec2) {
+        self.offset = offset;
+    }
+
+    fn set_layer(&mut self, layer: RenderLayer) {
+        self.layer = layer;
+    }
+
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    fn set_clip(&mut self, rect: Option<(f32, f32, f32, f32)>) {
+        // stored in screen pixels so it is unaffected by later camera changes
+        self.clip = rect.map(|r| {
+            let scale = self.view_scale;
+            let offset = self.view_offset + self.offset * scale;
+            (
+                r.0 * scale + offset.0,
+                r.1 * scale + offset.1,
+                r.2 * scale + offset.0,
+                r.3 * scale + offset.1,
+            )
+        });
+    }
+
     fn draw_texture(
         &mut self,
         texture: &TextureImage,
         position: Vec2,
         scale: f32,
         rotation: f32,
+        tint: (f32, f32, f32, f32),
     ) -> Result<(), Error> {
-        let tex_region = match self.atlas.get_texture_block(texture) {
-            Some(region) => region,
-            None => {
-                let region = self.atlas.add_texture(texture)?;
-                self.texture
-                    .set_region(texture.image(), (region[0], region[1]));
-                region
-            }
-        };
+        let (page, tex_region) = self.texture_region(texture)?;
         let size = (tex_region[2] - tex_region[0], tex_region[3] - tex_region[1]);
 
         let rect = (
@@ -149,13 +455,7 @@ where
             (ur, (tex_region[2], tex_region[3])),
             (lr, (tex_region[2], tex_region[1])),
         ];
-        for &(pos, tex_coord) in verts.iter() {
-            self.vertices.push(TexturedVertex {
-                position: pos,
-                tex_coord: (tex_coord.0 as f32, tex_coord.1 as f32),
-                color: (1.0, 1.0, 1.0, 1.0),
-            })
-        }
+        self.push_vertices(page, &verts, tint);
 
         Ok(())
     }
@@ -177,13 +477,21 @@ where
             (ur, (self.white_texture[2], self.white_texture[3])),
             (lr, (self.white_texture[2], self.white_texture[1])),
         ];
-        for &(pos, tex_coord) in verts.iter() {
-            self.vertices.push(TexturedVertex {
-                position: pos,
-                tex_coord: (tex_coord.0 as f32, tex_coord.1 as f32),
-                color: color,
-            })
+        self.push_vertices(0, &verts, color);
+
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, points: &[Vec2], color: (f32, f32, f32, f32)) -> Result<(), Error> {
+        let uv = (self.white_texture[0], self.white_texture[1]);
+        let mut verts = Vec::new();
+        // a fan of triangles around the first point
+        for i in 2..points.len() {
+            for p in &[points[0], points[i - 1], points[i]] {
+                verts.push(((p.0, p.1), uv));
+            }
         }
+        self.push_vertices(0, &verts, color);
 
         Ok(())
     }
@@ -192,37 +500,53 @@ where
         &mut self,
         center: Vec2,
         radius: f32,
-        points: i32,
         color: (f32, f32, f32, f32),
     ) -> Result<(), Error> {
-        let mut verts = Vec::new();
+        self.push_shape(center, Vec2::new(1.0, 0.0), (radius, radius), radius, 0.0, color);
 
-        let points = (0..points)
-            .map(|i| {
-                let a = i as f32 * (std::f32::consts::PI * 2.0 / points as f32);
-                center + Vec2::with_angle(a) * radius
-            })
-            .collect::<Vec<_>>();
-        let mut other_points = points.iter().skip(1).cloned().collect::<Vec<_>>();
-        other_points.push(points[0]);
-        for (p1, p2) in points.into_iter().zip(other_points.into_iter()) {
-            verts.extend_from_slice(&[
-                (
-                    (center.0, center.1),
-                    (self.white_texture[0], self.white_texture[1]),
-                ),
-                ((p1.0, p1.1), (self.white_texture[2], self.white_texture[1])),
-                ((p2.0, p2.1), (self.white_texture[2], self.white_texture[3])),
-            ])
-        }
+        Ok(())
+    }
 
-        for &(pos, tex_coord) in verts.iter() {
-            self.vertices.push(TexturedVertex {
-                position: pos,
-                tex_coord: (tex_coord.0 as f32, tex_coord.1 as f32),
-                color: color,
-            })
-        }
+    fn draw_ring(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        width: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error> {
+        self.push_shape(center, Vec2::new(1.0, 0.0), (radius, radius), radius, width, color);
+
+        Ok(())
+    }
+
+    fn draw_rounded_rect(
+        &mut self,
+        rect: (f32, f32, f32, f32),
+        radius: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error> {
+        let center = Vec2::new((rect.0 + rect.2) / 2.0, (rect.1 + rect.3) / 2.0);
+        let half_size = ((rect.2 - rect.0) / 2.0, (rect.3 - rect.1) / 2.0);
+        self.push_shape(center, Vec2::new(1.0, 0.0), half_size, radius, 0.0, color);
+
+        Ok(())
+    }
+
+    fn draw_capsule(
+        &mut self,
+        p1: Vec2,
+        p2: Vec2,
+        radius: f32,
+        color: (f32, f32, f32, f32),
+    ) -> Result<(), Error> {
+        let length = (p2 - p1).mag();
+        let axis = if length > 0.0 {
+            (p2 - p1) / length
+        } else {
+            Vec2::new(1.0, 0.0)
+        };
+        let half_size = (length / 2.0 + radius, radius);
+        self.push_shape((p1 + p2) / 2.0, axis, half_size, radius, 0.0, color);
 
         Ok(())
     }
@@ -252,14 +576,11 @@ where
             (ur, (self.white_texture[2], self.white_texture[3])),
             (lr, (self.white_texture[2], self.white_texture[1])),
         ];
-
-        for &(pos, tex_coord) in verts.iter() {
-            self.vertices.push(TexturedVertex {
-                position: (pos.0, pos.1),
-                tex_coord: (tex_coord.0 as f32, tex_coord.1 as f32),
-                color: color,
-            })
-        }
+        let verts = verts
+            .iter()
+            .map(|&(pos, tex_coord)| ((pos.0, pos.1), tex_coord))
+            .collect::<Vec<_>>();
+        self.push_vertices(0, &verts, color);
 
         Ok(())
     }
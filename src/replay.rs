@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use failure::{err_msg, Error};
+
+use controls::Controls;
+
+pub struct ReplayFrame {
+    pub dt: f32,
+    /// Size of the window the frame was played in, mouse positions are relative to it.
+    pub screen_size: Option<(i32, i32)>,
+    pub controls: Controls,
+}
+
+/// Recorded controls and frame times of a play session, one `<dt> <controls>` per line. A
+/// `size <width> <height>` line gives the window size of the frames after it.
+pub struct Replay {
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, Error> {
+        let source = fs::read_to_string(path)?;
+        let mut frames = Vec::new();
+        let mut screen_size = None;
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ' ');
+            let mut parse = || -> Result<Option<ReplayFrame>, Error> {
+                let first = parts.next().unwrap();
+                let rest = parts.next().unwrap_or("");
+                if first == "size" {
+                    let size = rest
+                        .split_whitespace()
+                        .map(|v| v.parse::<i32>())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match size[..] {
+                        [width, height] => screen_size = Some((width, height)),
+                        _ => return Err(err_msg("expected `size <width> <height>`")),
+                    }
+                    return Ok(None);
+                }
+
+                Ok(Some(ReplayFrame {
+                    dt: first.parse::<f32>()?,
+                    screen_size,
+                    controls: Controls::parse(rest)?,
+                }))
+            };
+            let frame = parse().map_err(|e| err_msg(format!("line {}: {}", i + 1, e)))?;
+            frames.extend(frame);
+        }
+
+        Ok(Replay { frames })
+    }
+
+    /// Window size of the first recorded frame.
+    pub fn screen_size(&self) -> Option<(i32, i32)> {
+        self.frames.first().and_then(|f| f.screen_size)
+    }
+}
+
+/// Writes every frame to a replay file as it is played.
+pub struct ReplayRecorder {
+    file: File,
+    screen_size: Option<(i32, i32)>,
+}
+
+impl ReplayRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<ReplayRecorder, Error> {
+        Ok(ReplayRecorder {
+            file: File::create(path)?,
+            screen_size: None,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        dt: f32,
+        screen_size: (i32, i32),
+        controls: &Controls,
+    ) -> Result<(), Error> {
+        if self.screen_size != Some(screen_size) {
+            writeln!(self.file, "size {} {}", screen_size.0, screen_size.1)?;
+            self.screen_size = Some(screen_size);
+        }
+        writeln!(self.file, "{} {}", dt, controls.to_line())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use embla::math::Vec2;
+
+// how fast trauma wears off per second
+const TRAUMA_DECAY: f32 = 1.5;
+// how fast a camera kick settles back per second
+const KICK_DECAY: f32 = 12.0;
+const SHAKE_FREQUENCY: f32 = 30.0;
+
+pub struct ShakeSettings {
+    /// Largest shake offset in world units, reached at full trauma.
+    pub intensity: f32,
+    /// Offset of a camera kick in world units.
+    pub kick_intensity: f32,
+    /// Disables all camera motion for players sensitive to it.
+    pub reduce_motion: bool,
+}
+
+impl Default for ShakeSettings {
+    fn default() -> ShakeSettings {
+        ShakeSettings {
+            intensity: 8.0,
+            kick_intensity: 3.0,
+            reduce_motion: false,
+        }
+    }
+}
+
+/// Trauma based screen shake.
+///
+/// Trauma is added by game events and decays over time, the shake offset grows with the square
+/// of the trauma so small hits barely register while big ones are violent. Kicks are short
+/// directional nudges that spring back.
+pub struct ScreenShake {
+    pub settings: ShakeSettings,
+    trauma: f32,
+    kick: Vec2,
+    time: f32,
+}
+
+impl ScreenShake {
+    pub fn new(settings: ShakeSettings) -> ScreenShake {
+        ScreenShake {
+            settings,
+            trauma: 0.0,
+            kick: Vec2::zero(),
+            time: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    pub fn kick(&mut self, direction: Vec2) {
+        self.kick = direction * self.settings.kick_intensity;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.kick = self.kick * (-KICK_DECAY * dt).exp();
+    }
+
+    pub fn offset(&self) -> Vec2 {
+        if self.settings.reduce_motion {
+            return Vec2::zero();
+        }
+
+        // sum of sines at unrelated frequencies, cheap and smooth enough for a shake
+        let t = self.time * SHAKE_FREQUENCY;
+        let noise = Vec2::new(
+            ((t * 1.0).sin() + (t * 2.3 + 1.7).sin()) * 0.5,
+            ((t * 1.3 + 4.1).sin() + (t * 2.9 + 0.3).sin()) * 0.5,
+        );
+        let shake = self.trauma * self.trauma * self.settings.intensity;
+
+        noise * shake + self.kick
+    }
+}
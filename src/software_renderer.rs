@@ -0,0 +1,464 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use embla::graphics::TextureImage;
+use embla::math::Vec2;
+use failure::Error;
+use png;
+use png::HasParameters;
+
+use camera::Camera;
+use components::RenderLayer;
+use render_interface::{BlendMode, RenderInterface};
+
+type Color = (f32, f32, f32, f32);
+
+const LAYER_COUNT: usize = RenderLayer::Hud as usize + 1;
+
+/// Renders on the CPU into an RGBA buffer, produces the same images as `GameRenderer` without
+/// needing a graphics context.
+///
+/// Every layer is rendered into its own buffer of premultiplied colors, the layers are
+/// composited back to front in `do_render`.
+pub struct SoftwareRenderer {
+    width: i32,
+    height: i32,
+    layers: Vec<Vec<[f32; 4]>>,
+    // the last rendered frame, 8 bit RGBA from the top row down
+    frame: Vec<u8>,
+    clear_color: Color,
+    view_scale: f32,
+    view_offset: Vec2,
+    offset: Vec2,
+    layer: RenderLayer,
+    blend_mode: BlendMode,
+    clip: Option<(f32, f32, f32, f32)>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(size: (i32, i32)) -> SoftwareRenderer {
+        let pixel_count = (size.0 * size.1) as usize;
+        SoftwareRenderer {
+            width: size.0,
+            height: size.1,
+            layers: vec![vec![[0.0; 4]; pixel_count]; LAYER_COUNT],
+            frame: vec![0; pixel_count * 4],
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            view_scale: 1.0,
+            view_offset: Vec2::zero(),
+            offset: Vec2::zero(),
+            layer: RenderLayer::Board,
+            blend_mode: BlendMode::Alpha,
+            clip: None,
+        }
+    }
+
+    /// Composites everything drawn since the last call into the frame and starts a new one.
+    pub fn do_render(&mut self) {
+        let clear = self.clear_color;
+        for i in 0..(self.width * self.height) as usize {
+            let mut color = [clear.0, clear.1, clear.2, 1.0];
+            for layer in self.layers.iter() {
+                let src = layer[i];
+                for (c, &s) in color.iter_mut().zip(src.iter()) {
+                    *c = s + *c * (1.0 - src[3]);
+                }
+            }
+
+            // flip so the frame starts with the top row
+            let (x, y) = (i as i32 % self.width, i as i32 / self.width);
+            let out = (((self.height - 1 - y) * self.width + x) * 4) as usize;
+            for (byte, &c) in self.frame[out..out + 4].iter_mut().zip(color.iter()) {
+                *byte = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+
+        for layer in self.layers.iter_mut() {
+            for pixel in layer.iter_mut() {
+                *pixel = [0.0; 4];
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    /// The last rendered frame as 8 bit RGBA, starting with the top row.
+    #[cfg(test)]
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.frame)?;
+
+        Ok(())
+    }
+
+    fn to_screen(&self, p: Vec2) -> Vec2 {
+        (p + self.offset) * self.view_scale + self.view_offset
+    }
+
+    // pixel range covered by a screen space bounding box, clipped to the screen and clip rect
+    fn pixel_bounds(&self, min: Vec2, max: Vec2) -> (i32, i32, i32, i32) {
+        let (mut x0, mut y0, mut x1, mut y1) = (min.0, min.1, max.0, max.1);
+        if let Some(clip) = self.clip {
+            x0 = x0.max(clip.0);
+            y0 = y0.max(clip.1);
+            x1 = x1.min(clip.2);
+            y1 = y1.min(clip.3);
+        }
+        (
+            (x0.floor() as i32).max(0),
+            (y0.floor() as i32).max(0),
+            (x1.ceil() as i32).min(self.width),
+            (y1.ceil() as i32).min(self.height),
+        )
+    }
+
+    fn blend(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+        let a = color.3 * coverage;
+        if a <= 0.0 {
+            return;
+        }
+        let additive = self.blend_mode == BlendMode::Additive;
+        let src = [
+            color.0 * a,
+            color.1 * a,
+            color.2 * a,
+            if additive { 0.0 } else { a },
+        ];
+        let dst = &mut self.layers[self.layer as usize][(y * self.width + x) as usize];
+        for c in 0..4 {
+            dst[c] = src[c] + dst[c] * (1.0 - src[3]);
+        }
+    }
+
+    // fills a screen space triangle, `shade` gives the color at barycentric coordinates
+    fn fill_triangle<F>(&mut self, points: [Vec2; 3], shade: F)
+    where
+        F: Fn(f32, f32, f32) -> Color,
+    {
+        let [a, b, c] = points;
+        let area = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+        if area == 0.0 {
+            return;
+        }
+
+        let min = Vec2::new(a.0.min(b.0).min(c.0), a.1.min(b.1).min(c.1));
+        let max = Vec2::new(a.0.max(b.0).max(c.0), a.1.max(b.1).max(c.1));
+        let (x0, y0, x1, y1) = self.pixel_bounds(min, max);
+        let edge =
+            |p: Vec2, q: Vec2, r: Vec2| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // sample at pixel centers like the GPU does
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(b, c, p) / area;
+                let w1 = edge(c, a, p) / area;
+                let w2 = edge(a, b, p) / area;
+                if w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0 {
+                    let color = shade(w0, w1, w2);
+                    self.blend(x, y, color, 1.0);
+                }
+            }
+        }
+    }
+
+    fn fill_quad(&mut self, quad: [Vec2; 4], color: Color) {
+        let quad = [
+            self.to_screen(quad[0]),
+            self.to_screen(quad[1]),
+            self.to_screen(quad[2]),
+            self.to_screen(quad[3]),
+        ];
+        self.fill_triangle([quad[0], quad[1], quad[2]], |_, _, _| color);
+        self.fill_triangle([quad[0], quad[2], quad[3]], |_, _, _| color);
+    }
+
+    // same signed distance field as shaders/shape_fragment.glsl
+    fn fill_shape(
+        &mut self,
+        center: Vec2,
+        axis: Vec2,
+        half_size: (f32, f32),
+        radius: f32,
+        outline: f32,
+        color: Color,
+    ) {
+        let radius = radius.min(half_size.0).min(half_size.1);
+        let up = Vec2::new(-axis.1, axis.0);
+        let extent = (half_size.0 * half_size.0 + half_size.1 * half_size.1).sqrt() + 1.0;
+        let screen_center = self.to_screen(center);
+        let screen_extent = Vec2::new(extent, extent) * self.view_scale;
+        let (x0, y0, x1, y1) =
+            self.pixel_bounds(screen_center - screen_extent, screen_center + screen_extent);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let world = (p - self.view_offset) / self.view_scale - self.offset - center;
+                let local = (
+                    world.0 * axis.0 + world.1 * axis.1,
+                    world.0 * up.0 + world.1 * up.1,
+                );
+
+                let q = (
+                    local.0.abs() - half_size.0 + radius,
+                    local.1.abs() - half_size.1 + radius,
+                );
+                let outside = (q.0.max(0.0) * q.0.max(0.0) + q.1.max(0.0) * q.1.max(0.0)).sqrt();
+                let mut d = outside + q.0.max(q.1).min(0.0) - radius;
+                if outline > 0.0 {
+                    d = (d + outline * 0.5).abs() - outline * 0.5;
+                }
+
+                let coverage = (0.5 - d * self.view_scale).clamp(0.0, 1.0);
+                self.blend(x, y, color, coverage);
+            }
+        }
+    }
+}
+
+impl RenderInterface for SoftwareRenderer {
+    fn screen_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
+
+    fn set_camera(&mut self, camera: &Camera) -> Result<(), Error> {
+        self.view_scale = camera.scale();
+        self.view_offset = camera.offset();
+
+        Ok(())
+    }
+
+    fn set_offset(&mut self, offset: Vec2) {
+        self.offset = offset;
+    }
+
+    fn set_layer(&mut self, layer: RenderLayer) {
+        self.layer = layer;
+    }
+
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    fn set_clip(&mut self, rect: Option<(f32, f32, f32, f32)>) {
+        self.clip = rect.map(|r| {
+            let min = self.to_screen(Vec2::new(r.0, r.1));
+            let max = self.to_screen(Vec2::new(r.2, r.3));
+            (min.0, min.1, max.0, max.1)
+        });
+    }
+
+    fn draw_texture(
+        &mut self,
+        texture: &TextureImage,
+        position: Vec2,
+        scale: f32,
+        rotation: f32,
+        tint: Color,
+    ) -> Result<(), Error> {
+        let image = texture.image();
+        let size = Vec2::new(image.width as f32, image.height as f32);
+        let rotate = |v: Vec2| {
+            let (s, c) = (rotation.sin(), rotation.cos());
+            Vec2::new(v.0 * c - v.1 * s, v.0 * s + v.1 * c)
+        };
+        let corner = |x: f32, y: f32| {
+            let local = Vec2::new((x - 0.5) * size.0, (y - 0.5) * size.1) * scale;
+            self.to_screen(position + rotate(local))
+        };
+        let quad = [
+            corner(0.0, 0.0),
+            corner(0.0, 1.0),
+            corner(1.0, 1.0),
+            corner(1.0, 0.0),
+        ];
+        let uvs = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, size.1),
+            Vec2::new(size.0, size.1),
+            Vec2::new(size.0, 0.0),
+        ];
+
+        // nearest neighbour sampling, the first row of the image is at the bottom
+        let sample = |uv: Vec2| {
+            let x = (uv.0 as u32).min(image.width - 1);
+            let y = (uv.1 as u32).min(image.height - 1);
+            let i = ((y * image.width + x) * 4) as usize;
+            let p = &image.data[i..i + 4];
+            (
+                p[0] as f32 / 255.0 * tint.0,
+                p[1] as f32 / 255.0 * tint.1,
+                p[2] as f32 / 255.0 * tint.2,
+                p[3] as f32 / 255.0 * tint.3,
+            )
+        };
+        for &(i, j, k) in [(0, 1, 2), (0, 2, 3)].iter() {
+            let (a, b, c) = (uvs[i], uvs[j], uvs[k]);
+            self.fill_triangle([quad[i], quad[j], quad[k]], |w0, w1, w2| {
+                sample(a * w0 + b * w1 + c * w2)
+            });
+        }
+
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: (f32, f32, f32, f32), color: Color) -> Result<(), Error> {
+        self.fill_quad(
+            [
+                Vec2::new(rect.0, rect.1),
+                Vec2::new(rect.0, rect.3),
+                Vec2::new(rect.2, rect.3),
+                Vec2::new(rect.2, rect.1),
+            ],
+            color,
+        );
+
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, points: &[Vec2], color: Color) -> Result<(), Error> {
+        let points: Vec<Vec2> = points.iter().map(|&p| self.to_screen(p)).collect();
+        for i in 2..points.len() {
+            self.fill_triangle([points[0], points[i - 1], points[i]], |_, _, _| color);
+        }
+
+        Ok(())
+    }
+
+    fn draw_circle(&mut self, center: Vec2, radius: f32, color: Color) -> Result<(), Error> {
+        self.fill_shape(center, Vec2::new(1.0, 0.0), (radius, radius), radius, 0.0, color);
+
+        Ok(())
+    }
+
+    fn draw_ring(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        width: f32,
+        color: Color,
+    ) -> Result<(), Error> {
+        self.fill_shape(center, Vec2::new(1.0, 0.0), (radius, radius), radius, width, color);
+
+        Ok(())
+    }
+
+    fn draw_rounded_rect(
+        &mut self,
+        rect: (f32, f32, f32, f32),
+        radius: f32,
+        color: Color,
+    ) -> Result<(), Error> {
+        let center = Vec2::new((rect.0 + rect.2) / 2.0, (rect.1 + rect.3) / 2.0);
+        let half_size = ((rect.2 - rect.0) / 2.0, (rect.3 - rect.1) / 2.0);
+        self.fill_shape(center, Vec2::new(1.0, 0.0), half_size, radius, 0.0, color);
+
+        Ok(())
+    }
+
+    fn draw_capsule(&mut self, p1: Vec2, p2: Vec2, radius: f32, color: Color) -> Result<(), Error> {
+        let length = (p2 - p1).mag();
+        let axis = if length > 0.0 {
+            (p2 - p1) / length
+        } else {
+            Vec2::new(1.0, 0.0)
+        };
+        let half_size = (length / 2.0 + radius, radius);
+        self.fill_shape((p1 + p2) / 2.0, axis, half_size, radius, 0.0, color);
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, p1: Vec2, p2: Vec2, width: f32, color: Color) -> Result<(), Error> {
+        let length = (p2 - p1).mag();
+        if length == 0.0 {
+            return Ok(());
+        }
+        let normal = Vec2::new(-(p2 - p1).1, (p2 - p1).0) / length * (width / 2.0);
+        self.fill_quad([p1 - normal, p1 + normal, p2 + normal, p2 - normal], color);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use controls::Controls;
+    use game::Game;
+    use level::{Level, LEVEL_PATH};
+    use software_renderer::SoftwareRenderer;
+    use sprites::load_png;
+    use SNAPSHOT_SIZE;
+
+    // largest difference per color channel that still counts as the same image
+    const TOLERANCE: i32 = 2;
+
+    /// Compares the last frame with `tests/snapshots/<name>.png`, run with
+    /// `UPDATE_SNAPSHOTS=1` to write the current frame as the new snapshot instead.
+    fn assert_snapshot(renderer: &SoftwareRenderer, name: &str) {
+        let path = format!("{}/tests/snapshots/{}.png", env!("CARGO_MANIFEST_DIR"), name);
+        if env::var("UPDATE_SNAPSHOTS").is_ok() {
+            renderer.save_png(&path).unwrap();
+            return;
+        }
+
+        let expected = load_png(&path).unwrap();
+        let (width, height) = renderer.size();
+        assert_eq!((expected.width, expected.height), (width as u32, height as u32));
+        let different = renderer
+            .frame()
+            .chunks(4)
+            .zip(expected.data.chunks(4))
+            .filter(|&(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(&a, &b)| (a as i32 - b as i32).abs() > TOLERANCE)
+            })
+            .count();
+        if different > 0 {
+            renderer.save_png(format!("{}.actual.png", path)).unwrap();
+            panic!("{} pixels differ from {}", different, path);
+        }
+    }
+
+    #[test]
+    fn board() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        let mut renderer = SoftwareRenderer::new(SNAPSHOT_SIZE);
+        game.render(&mut renderer).unwrap();
+        renderer.do_render();
+
+        assert_snapshot(&renderer, "board");
+    }
+
+    #[test]
+    fn blobs_moving() {
+        let mut game = Game::new(&Level::load(LEVEL_PATH).unwrap()).unwrap();
+        let controls = Controls::parse("0 0 0").unwrap();
+        for _ in 0..60 {
+            game.update(1.0 / 30.0, &controls).unwrap();
+        }
+        let mut renderer = SoftwareRenderer::new(SNAPSHOT_SIZE);
+        game.render(&mut renderer).unwrap();
+        renderer.do_render();
+
+        assert_snapshot(&renderer, "blobs_moving");
+    }
+}
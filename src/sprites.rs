@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::rc::Rc;
+
+use embla::assets::Image;
+use embla::graphics::TextureImage;
+use failure::{err_msg, Error};
+use png::{self, HasParameters};
+
+// an image path and the size of its frames
+type SheetKey = (String, Option<(u32, u32)>);
+
+/// Loaded sprite images, split into their animation frames.
+pub struct Sprites {
+    sheets: HashMap<SheetKey, Vec<TextureImage>>,
+}
+
+impl Sprites {
+    pub fn new() -> Sprites {
+        Sprites {
+            sheets: HashMap::new(),
+        }
+    }
+
+    /// Loads an image unless it is already loaded and returns its frames.
+    pub fn load(
+        &mut self,
+        path: &str,
+        frame_size: Option<(u32, u32)>,
+    ) -> Result<&[TextureImage], Error> {
+        let key = (path.to_string(), frame_size);
+        if !self.sheets.contains_key(&key) {
+            let image = load_png(path)?;
+            let frames = match frame_size {
+                Some(size) => split_frames(&image, size)?,
+                None => vec![image],
+            };
+            let frames = frames
+                .into_iter()
+                .map(|frame| TextureImage::new(Rc::new(flip_rows(frame))))
+                .collect();
+            self.sheets.insert(key.clone(), frames);
+        }
+
+        Ok(&self.sheets[&key])
+    }
+
+    pub fn frames(&self, path: &str, frame_size: Option<(u32, u32)>) -> Option<&[TextureImage]> {
+        self.sheets
+            .get(&(path.to_string(), frame_size))
+            .map(|frames| &frames[..])
+    }
+}
+
+/// Decodes a PNG file into 8 bit RGBA.
+pub fn load_png(path: &str) -> Result<Image, Error> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    // 16 bit channels are cut down to their high byte, textures are 8 bits per channel
+    decoder.set(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info()?;
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(err_msg(format!(
+            "{}: unsupported bit depth {:?}",
+            path, info.bit_depth
+        )));
+    }
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+
+    let data = match info.color_type {
+        png::ColorType::RGBA => buffer,
+        png::ColorType::RGB => buffer
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks(2)
+            .flat_map(|p| vec![p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| vec![v, v, v, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(err_msg(format!("{}: unexpanded indexed color", path)));
+        }
+    };
+
+    Ok(Image {
+        data,
+        width: info.width,
+        height: info.height,
+    })
+}
+
+fn split_frames(image: &Image, (width, height): (u32, u32)) -> Result<Vec<Image>, Error> {
+    if width == 0 || height == 0 || width > image.width || height > image.height {
+        return Err(err_msg(format!(
+            "frame size {}x{} does not fit in a {}x{} image",
+            width, height, image.width, image.height
+        )));
+    }
+
+    let mut frames = Vec::new();
+    for row in 0..image.height / height {
+        for column in 0..image.width / width {
+            let mut data = Vec::with_capacity((width * height * 4) as usize);
+            for y in row * height..(row + 1) * height {
+                let start = ((y * image.width + column * width) * 4) as usize;
+                data.extend_from_slice(&image.data[start..start + (width * 4) as usize]);
+            }
+            frames.push(Image {
+                data,
+                width,
+                height,
+            });
+        }
+    }
+
+    Ok(frames)
+}
+
+// images are stored top down but the renderer expects the bottom row first
+fn flip_rows(image: Image) -> Image {
+    let row_size = (image.width * 4) as usize;
+    let data = image
+        .data
+        .chunks(row_size)
+        .rev()
+        .flat_map(|row| row.iter().cloned())
+        .collect();
+    Image { data, ..image }
+}
@@ -0,0 +1,57 @@
+use embla::ecs::{EntityId, World};
+use failure::Error;
+
+use components::{Animation, AnimationState, AnimationTiming, Sprite};
+
+/// Advances animations and updates sprite frames, `beat` is the current position in the song in
+/// beats.
+pub fn animate_sprites(
+    dt: f32,
+    beat: f32,
+    beat_time: f32,
+    world: &mut World,
+) -> Result<(), Error> {
+    let mut removed = Vec::new();
+    for (e, mut animation, mut sprite) in
+        world.with_components::<(EntityId, Animation, Sprite)>()
+    {
+        animation.elapsed += dt;
+        animation.elapsed_beats += dt / beat_time;
+
+        let (frame, finished) = match animation.clip() {
+            Some(clip) if !clip.frames.is_empty() => {
+                // dying copies go away after one pass even if their clip loops
+                let once = !clip.looping || animation.state == AnimationState::Dying;
+                let cycles = match clip.timing {
+                    AnimationTiming::Fps(fps) => {
+                        animation.elapsed * fps / clip.frames.len() as f32
+                    }
+                    AnimationTiming::Beats(beats) if !once => beat / beats,
+                    AnimationTiming::Beats(beats) => animation.elapsed_beats / beats,
+                };
+                if once && cycles >= 1.0 {
+                    (clip.frames[clip.frames.len() - 1], true)
+                } else {
+                    let index = (cycles.fract() * clip.frames.len() as f32) as usize;
+                    (clip.frames[index.min(clip.frames.len() - 1)], false)
+                }
+            }
+            _ => continue,
+        };
+        sprite.frame = frame;
+
+        if finished {
+            match animation.state {
+                AnimationState::Dying => removed.push(e.0),
+                AnimationState::Hit => animation.set_state(AnimationState::Idle),
+                _ => {}
+            }
+        }
+    }
+
+    for e in removed {
+        world.remove_entity(e);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Blob, BlobGoal, BlobSpawn, ColoredCircle, ColoredPolygon, FillMode, Pad, TeamGate,
+                 TilePosition};
+use grid::Grid;
+use theme::Theme;
+
+/// Restyles existing entities after the theme has changed.
+pub fn apply_theme(theme: &Theme, grid: &Grid, world: &mut World) -> Result<(), Error> {
+    let cell_size = grid.cell_width();
+
+    let gap = theme.grid_gap;
+    for (mut polygon, tile) in world.with_components::<(ColoredPolygon, TilePosition)>() {
+        polygon.points = grid.centered_outline((tile.0, tile.1), gap);
+        polygon.color = theme.wall_color;
+    }
+    for (mut circle, _) in world.with_components::<(ColoredCircle, BlobSpawn)>() {
+        circle.radius = cell_size * theme.spawn_radius;
+        circle.fill = FillMode::Outline(theme.spawn_outline);
+    }
+    for (mut circle, _) in world.with_components::<(ColoredCircle, BlobGoal)>() {
+        circle.radius = cell_size * theme.goal_radius;
+        circle.fill = FillMode::Outline(theme.goal_outline);
+    }
+    for (mut circle, _) in world.with_components::<(ColoredCircle, TeamGate)>() {
+        circle.radius = cell_size * theme.gate_radius;
+        circle.fill = FillMode::Outline(theme.gate_outline);
+    }
+    for (mut circle, _) in world.with_components::<(ColoredCircle, Pad)>() {
+        circle.fill = FillMode::Outline(theme.pad_outline);
+    }
+    for (mut circle, _) in world.with_components::<(ColoredCircle, Blob)>() {
+        circle.radius = theme.blob_radius;
+    }
+
+    Ok(())
+}
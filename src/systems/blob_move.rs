@@ -1,50 +1,469 @@
+use std::collections::{HashMap, HashSet};
+
 use embla::ecs::{EntityId, World};
-use embla::math::Vec2;
 use failure::Error;
 
-use components::{Blob, Position, TilePosition};
-use grid::Grid;
+use components::{Animation, AnimationState, Blob, BlobGoal, PadTeam, Position, TeamGate,
+                 TeamSet, TilePosition};
+use grid::{Grid, PathNode};
+use terrain::{Terrain, MUD_HOLD};
 
 pub fn tween_blobs(beat_timer: f32, grid: &Grid, world: &mut World) -> Result<(), Error> {
-    for (mut position, _, blob) in world.with_components::<(Position, TilePosition, Blob)>() {
-        let center_offset = Vec2::new(
-            grid.cell_width() as f32 * 0.5,
-            grid.cell_height() as f32 * 0.5,
-        );
-        let tile_position = |t: (i32, i32)| {
-            let r = grid.cell_rect(t.0, t.1);
-            Vec2::new(r.0 as f32, r.1 as f32)
-        };
+    for (mut position, _, mut blob) in world.with_components::<(Position, TilePosition, Blob)>() {
+        let tile_position = |t: (i32, i32)| grid.cell_center(t);
+        // blobs fade out and back in when teleporting instead of sliding across the board
+        let teleports = |from: (i32, i32), to: (i32, i32)| grid.teleporter(&from) == Some(to);
+
+        let here = blob.path[blob.path_index];
+        let prev = blob.path[blob.path_index.max(1) - 1];
+        let next = blob.path[(blob.path_index + 1).min(blob.path.len() - 1)];
+        let still = (0.0, tile_position(here), tile_position(here));
 
+        blob.visibility = 1.0;
         let (timer, from, to) = if beat_timer <= 0.5 {
-            let prev = tile_position(blob.path[(blob.path_index.max(1) - 1)]);
-            let next = tile_position(blob.path[blob.path_index]);
-            (beat_timer / 0.5, prev + (next - prev) * 0.5, next)
+            let t = beat_timer / 0.5;
+            // bumping into a full tile bounces back from halfway there
+            if blob.bumped && teleports(here, next) {
+                blob.visibility = t;
+                still
+            } else if blob.bumped {
+                let (here, next) = (tile_position(here), tile_position(next));
+                (t, here + (next - here) * 0.5, here)
+            } else if blob.waited {
+                still
+            } else if teleports(prev, here) {
+                blob.visibility = t;
+                still
+            } else {
+                let (prev, next) = (tile_position(prev), tile_position(here));
+                (t, prev + (next - prev) * 0.5, next)
+            }
         } else {
-            let prev = tile_position(blob.path[blob.path_index]);
-            let next = tile_position(blob.path[(blob.path_index + 1).min(blob.path.len() - 1)]);
-            ((beat_timer - 0.5) / 0.5, prev, next - (next - prev) * 0.5)
+            let t = (beat_timer - 0.5) / 0.5;
+            if blob.held > 0 || blob.hold.is_some() {
+                still
+            } else if teleports(here, next) {
+                blob.visibility = 1.0 - t;
+                still
+            } else {
+                let (prev, next) = (tile_position(here), tile_position(next));
+                (t, prev, next - (next - prev) * 0.5)
+            }
         };
 
-        position.0 = (from + (to - from) * timer) + center_offset;
+        position.0 = from + (to - from) * timer;
     }
 
     Ok(())
 }
 
-pub fn move_blobs(world: &mut World) -> Result<(), Error> {
+/// Moves every blob one step along its path, returns the number of blobs that reached their goal.
+pub fn move_blobs(grid: &Grid, world: &mut World) -> Result<usize, Error> {
+    let gates: Vec<(TilePosition, PadTeam)> = world
+        .with_components::<(TeamGate, TilePosition, PadTeam)>()
+        .map(|(_, tile, team)| (*tile, *team))
+        .collect();
+    let goals = goals(world);
+
+    // walls may have gone up since the last beat
+    route_blobs(grid, world)?;
+    let queued = match grid.tile_capacity() {
+        Some(capacity) => queue_blobs(capacity, &goals, world),
+        None => HashSet::new(),
+    };
+
     let mut removed = Vec::new();
-    for (e, mut tile_pos, mut blob) in world.with_components::<(EntityId, TilePosition, Blob)>() {
-        blob.path_index += 1;
-        if blob.path_index >= blob.path.len() {
+    let mut moved = HashSet::new();
+    for (e, mut tile_pos, mut blob, mut team) in
+        world.with_components::<(EntityId, TilePosition, Blob, PadTeam)>()
+    {
+        // long blobs being held stay on the pad
+        if blob.hold.is_some() {
+            blob.waited = true;
+            blob.bumped = false;
+            continue;
+        }
+        if goal_of(&goals, *team) == Some(blob.path[blob.path_index]) {
             removed.push(e.0);
+            continue;
+        }
+        blob.bumped = blob.held == 0 && queued.contains(&e.0);
+        // blobs with no way to their goal wait for one to open up
+        let stuck = blob.path_index + 1 >= blob.path.len();
+        blob.waited = blob.held > 0 || blob.bumped || stuck;
+        if blob.held > 0 {
+            blob.held -= 1;
+            continue;
+        }
+        if blob.waited {
+            continue;
+        }
+
+        blob.path_index += 1;
+        let pos = blob.path[blob.path_index];
+        if (tile_pos.0, tile_pos.1) != pos {
+            moved.insert(e.0);
+        }
+        *tile_pos = TilePosition(pos.0, pos.1);
+        if grid.terrain(&pos) == Some(Terrain::Mud) {
+            blob.held = MUD_HOLD;
+        }
+        let gate = gates.iter().find(|&&(tile, _)| tile == *tile_pos);
+        if let Some(&(_, gate_team)) = gate.filter(|&&(_, gate_team)| gate_team != *team) {
+            // blobs that changed team head for their new goal from where they are, chords keep
+            // every team that has to hit them
+            if blob.teams.teams().len() == 1 {
+                blob.teams = TeamSet::of(&[gate_team]);
+                *team = gate_team;
+            } else if blob.teams.contains(gate_team) {
+                *team = gate_team;
+            }
+        }
+    }
+    // tweening shows where blobs head next
+    route_blobs(grid, world)?;
+
+    // blobs that waited, were held or are stuck idle until they step again
+    for (e, _, mut animation) in world.with_components::<(EntityId, Blob, Animation)>() {
+        if moved.contains(&e.0) {
+            animation.set_state(AnimationState::Moving);
         } else {
-            let pos = blob.path[blob.path_index];
-            *tile_pos = TilePosition(pos.0, pos.1);
+            animation.set_state(AnimationState::Idle);
         }
     }
+
+    let reached_goal = removed.len();
     for e in removed {
         world.remove_entity(e);
     }
+    Ok(reached_goal)
+}
+
+/// Replaces the rest of every blob's path with the way to its goal from where it is now, read
+/// from the grid's flow fields. Blobs with no way to their goal are left with no steps ahead.
+fn route_blobs(grid: &Grid, world: &mut World) -> Result<(), Error> {
+    let goals = goals(world);
+    for (mut blob, team) in world.with_components::<(Blob, PadTeam)>() {
+        let node = path_node(grid, &blob);
+        let route = goal_of(&goals, *team).and_then(|goal| {
+            grid.flow_path_from(node, goal)
+                // a wall can cut a slide short, then the blob starts over from standing still
+                .or_else(|| grid.flow_path(node.0, goal))
+        });
+
+        let index = blob.path_index;
+        blob.path.truncate(index + 1);
+        if let Some(route) = route {
+            blob.path.extend(route.into_iter().skip(1));
+        }
+    }
+
     Ok(())
 }
+
+/// The tile a blob is on paired with the direction it is sliding in, blobs keep sliding across
+/// ice in the direction they stepped onto it.
+fn path_node(grid: &Grid, blob: &Blob) -> PathNode {
+    let here = blob.path[blob.path_index];
+    if blob.path_index == 0 || grid.terrain(&here) != Some(Terrain::Ice) {
+        return (here, (0, 0));
+    }
+    let prev = blob.path[blob.path_index - 1];
+    if grid.teleporter(&prev) == Some(here) {
+        return (here, (0, 0));
+    }
+    (here, (here.0 - prev.0, here.1 - prev.1))
+}
+
+fn goals(world: &World) -> Vec<(PadTeam, (i32, i32))> {
+    world
+        .with_components::<(TilePosition, PadTeam, BlobGoal)>()
+        .map(|(tile, team, _)| (*team, (tile.0, tile.1)))
+        .collect()
+}
+
+fn goal_of(goals: &[(PadTeam, (i32, i32))], team: PadTeam) -> Option<(i32, i32)> {
+    goals
+        .iter()
+        .find(|&&(t, _)| t == team)
+        .map(|&(_, tile)| tile)
+}
+
+/// Finds the blobs that have to wait this beat because the tile ahead is full. Blobs move in
+/// spawn order, so a blob leaving a tile makes room for the one behind it on the same beat.
+fn queue_blobs(
+    capacity: usize,
+    goals: &[(PadTeam, (i32, i32))],
+    world: &World,
+) -> HashSet<usize> {
+    let mut blobs = world
+        .with_components::<(EntityId, Blob, PadTeam)>()
+        .map(|(e, blob, team)| {
+            let here = blob.path[blob.path_index];
+            // held and stuck blobs stay where they are, blobs on their goal leave the board
+            let next = if goal_of(goals, *team) == Some(here) {
+                None
+            } else if blob.held > 0 || blob.hold.is_some() {
+                Some(here)
+            } else {
+                Some(*blob.path.get(blob.path_index + 1).unwrap_or(&here))
+            };
+            (blob.spawn_index, e.0, here, next)
+        })
+        .collect::<Vec<_>>();
+    blobs.sort_by_key(|&(spawn_index, _, _, _)| spawn_index);
+
+    let mut counts: HashMap<(i32, i32), usize> = HashMap::new();
+    for &(_, _, here, _) in &blobs {
+        *counts.entry(here).or_insert(0) += 1;
+    }
+
+    let mut queued = HashSet::new();
+    let mut processed = HashSet::new();
+    for &(_, id, here, next) in &blobs {
+        if processed.contains(&id) {
+            continue;
+        }
+        match next {
+            Some(next) if next == here => {}
+            None => *counts.get_mut(&here).unwrap() -= 1,
+            Some(next) if counts.get(&next).cloned().unwrap_or(0) < capacity => {
+                *counts.get_mut(&here).unwrap() -= 1;
+                *counts.entry(next).or_insert(0) += 1;
+            }
+            Some(next) => {
+                // blobs walking into each other swap places instead of both waiting forever
+                let swap = blobs.iter().find(|&&(_, other, there, other_next)| {
+                    other != id && there == next && other_next == Some(here)
+                        && (!processed.contains(&other) || queued.contains(&other))
+                });
+                match swap {
+                    Some(&(_, other, _, _)) => {
+                        queued.remove(&other);
+                        processed.insert(other);
+                    }
+                    None => {
+                        queued.insert(id);
+                    }
+                }
+            }
+        }
+        processed.insert(id);
+    }
+
+    queued
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::{EntityId, World};
+
+    use components::{Animation, AnimationState, Blob, BlobGoal, PadTeam, TeamGate, TeamSet,
+                     TilePosition};
+    use grid::Grid;
+    use systems::{find_goal, move_blobs};
+
+    fn add_goal(world: &mut World, team: PadTeam, tile: (i32, i32)) {
+        world
+            .add_entity()
+            .insert(TilePosition(tile.0, tile.1))
+            .insert(team)
+            .insert(BlobGoal);
+    }
+
+    fn add_blob(grid: &Grid, world: &mut World, spawn_index: u32, team: PadTeam, tile: (i32, i32)) {
+        add_chord(grid, world, spawn_index, &[team], tile);
+    }
+
+    // the first team is the one whose goal the blob heads for
+    fn add_chord(
+        grid: &Grid,
+        world: &mut World,
+        spawn_index: u32,
+        teams: &[PadTeam],
+        tile: (i32, i32),
+    ) {
+        let team = teams[0];
+        let goal = find_goal(team, world).unwrap();
+        world
+            .add_entity()
+            .insert(TilePosition(tile.0, tile.1))
+            .insert(team)
+            .insert(Blob {
+                spawn_index,
+                path_index: 0,
+                path: grid.flow_path(tile, goal).unwrap(),
+                held: 0,
+                waited: false,
+                bumped: false,
+                visibility: 1.0,
+                length: 1,
+                hold: None,
+                teams: TeamSet::of(teams),
+            });
+    }
+
+    // tiles of the blobs in spawn order
+    fn tiles(world: &World) -> Vec<(i32, i32)> {
+        let mut blobs = world
+            .with_components::<(Blob, TilePosition)>()
+            .map(|(blob, tile)| (blob.spawn_index, (tile.0, tile.1)))
+            .collect::<Vec<_>>();
+        blobs.sort();
+        blobs.into_iter().map(|(_, tile)| tile).collect()
+    }
+
+    #[test]
+    fn blobs_go_around_walls_put_up_after_they_spawned() {
+        let (mut grid, mut world) = (Grid::new(5, 5, 10, 10), World::new());
+        add_goal(&mut world, PadTeam::Red, (2, 0));
+        add_blob(&grid, &mut world, 0, PadTeam::Red, (2, 4));
+        grid.set_occupied((2, 3), true);
+
+        move_blobs(&grid, &mut world).unwrap();
+        let here = tiles(&world)[0];
+        assert!(here == (1, 4) || here == (3, 4), "{:?}", here);
+
+        let mut reached_goal = 0;
+        for _ in 0..6 {
+            reached_goal += move_blobs(&grid, &mut world).unwrap();
+        }
+        assert_eq!(reached_goal, 1);
+    }
+
+    #[test]
+    fn walled_in_blobs_wait() {
+        let (mut grid, mut world) = (Grid::new(5, 5, 10, 10), World::new());
+        add_goal(&mut world, PadTeam::Red, (2, 0));
+        add_blob(&grid, &mut world, 0, PadTeam::Red, (0, 4));
+        grid.set_occupied((0, 3), true);
+        grid.set_occupied((1, 4), true);
+
+        assert_eq!(move_blobs(&grid, &mut world).unwrap(), 0);
+        assert_eq!(tiles(&world), vec![(0, 4)]);
+
+        grid.set_occupied((1, 4), false);
+        move_blobs(&grid, &mut world).unwrap();
+        assert_eq!(tiles(&world), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn blobs_only_play_moving_when_they_step() {
+        let (mut grid, mut world) = (Grid::new(5, 5, 10, 10), World::new());
+        add_goal(&mut world, PadTeam::Red, (2, 0));
+        let goal = find_goal(PadTeam::Red, &world).unwrap();
+        world
+            .add_entity()
+            .insert(TilePosition(0, 4))
+            .insert(PadTeam::Red)
+            .insert(Animation::new(Vec::new()))
+            .insert(Blob {
+                spawn_index: 0,
+                path_index: 0,
+                path: grid.flow_path((0, 4), goal).unwrap(),
+                held: 0,
+                waited: false,
+                bumped: false,
+                visibility: 1.0,
+                length: 1,
+                hold: None,
+                teams: TeamSet::of(&[PadTeam::Red]),
+            });
+        let state = |world: &World| {
+            world
+                .with_components::<(Blob, Animation)>()
+                .map(|(_, animation)| animation.state)
+                .next()
+                .unwrap()
+        };
+
+        move_blobs(&grid, &mut world).unwrap();
+        assert_eq!(state(&world), AnimationState::Moving);
+
+        let here = tiles(&world)[0];
+        for &(dx, dy) in &[(1, 0), (-1, 0), (0, -1), (0, 1)] {
+            grid.set_occupied((here.0 + dx, here.1 + dy), true);
+        }
+        move_blobs(&grid, &mut world).unwrap();
+        assert_eq!(tiles(&world), vec![here]);
+        assert_eq!(state(&world), AnimationState::Idle);
+    }
+
+    #[test]
+    fn older_blobs_go_first_into_a_full_tile() {
+        // both blobs have to go through (2, 2) to get past the wall
+        for &(older, younger) in &[((1, 2), (3, 2)), ((3, 2), (1, 2))] {
+            let (mut grid, mut world) = (Grid::new(5, 3, 10, 10), World::new());
+            for &x in &[0, 1, 3, 4] {
+                grid.set_occupied((x, 1), true);
+            }
+            grid.set_tile_capacity(Some(1));
+            add_goal(&mut world, PadTeam::Red, (2, 0));
+            add_blob(&grid, &mut world, 1, PadTeam::Red, younger);
+            add_blob(&grid, &mut world, 0, PadTeam::Red, older);
+
+            move_blobs(&grid, &mut world).unwrap();
+            assert_eq!(tiles(&world), vec![(2, 2), younger]);
+            move_blobs(&grid, &mut world).unwrap();
+            assert_eq!(tiles(&world), vec![(2, 1), (2, 2)]);
+        }
+    }
+
+    #[test]
+    fn blobs_walking_into_each_other_swap_tiles() {
+        let (mut grid, mut world) = (Grid::new(5, 1, 10, 10), World::new());
+        grid.set_tile_capacity(Some(1));
+        add_goal(&mut world, PadTeam::Red, (0, 0));
+        add_goal(&mut world, PadTeam::Blue, (4, 0));
+        add_blob(&grid, &mut world, 0, PadTeam::Red, (2, 0));
+        add_blob(&grid, &mut world, 1, PadTeam::Blue, (1, 0));
+
+        move_blobs(&grid, &mut world).unwrap();
+        assert_eq!(tiles(&world), vec![(1, 0), (2, 0)]);
+        let bumped = world
+            .with_components::<(EntityId, Blob)>()
+            .filter(|(_, blob)| blob.bumped)
+            .count();
+        assert_eq!(bumped, 0);
+    }
+
+    fn teams(world: &World) -> Vec<(PadTeam, TeamSet)> {
+        world
+            .with_components::<(Blob, PadTeam)>()
+            .map(|(blob, team)| (*team, blob.teams))
+            .collect()
+    }
+
+    #[test]
+    fn gates_switch_single_blobs_and_keep_chords_whole() {
+        // the gate is the only way past the wall
+        let mut grid = Grid::new(3, 3, 10, 10);
+        grid.set_occupied((0, 1), true);
+        grid.set_occupied((2, 1), true);
+
+        let (red, green, blue) = (PadTeam::Red, PadTeam::Green, PadTeam::Blue);
+        let cases = [
+            (vec![red], green, vec![green]),
+            (vec![red, green], green, vec![red, green]),
+            (vec![red, blue], red, vec![red, blue]),
+        ];
+        for &(ref before, after, ref members) in &cases {
+            let mut world = World::new();
+            world
+                .add_entity()
+                .insert(TilePosition(1, 1))
+                .insert(green)
+                .insert(TeamGate);
+            add_goal(&mut world, red, (1, 0));
+            add_goal(&mut world, green, (0, 0));
+            add_goal(&mut world, blue, (2, 0));
+            add_chord(&grid, &mut world, 0, before, (1, 2));
+
+            move_blobs(&grid, &mut world).unwrap();
+            assert_eq!(tiles(&world), vec![(1, 1)]);
+            let teams = teams(&world);
+            assert!(teams[0].0 == after);
+            assert_eq!(teams[0].1, TeamSet::of(members));
+        }
+    }
+}
@@ -1,58 +1,160 @@
-use embla::ecs::World;
+use std::collections::HashMap;
+
+use embla::ecs::{EntityId, World};
 use embla::math::Vec2;
 use failure::Error;
 
-use components::{Blob, BlobGoal, BlobSpawn, ColoredCircle, FillMode, PadTeam, Position,
-                 TilePosition};
+use components::{Animation, Blob, BlobGoal, BlobSpawn, ColoredCircle, FillMode, Note, PadTeam,
+                 Position, RenderLayer, Sprite, TeamSet, TilePosition};
 use grid::Grid;
+use theme::Theme;
+
+pub fn spawn_blobs(
+    grid: &Grid,
+    theme: &Theme,
+    sprite: Option<&Sprite>,
+    animation: Option<&Animation>,
+    world: &mut World,
+) -> Result<(), Error> {
+    let mut crowds: HashMap<(i32, i32), usize> = HashMap::new();
+    for (tile_pos, _) in world.with_components::<(TilePosition, Blob)>() {
+        *crowds.entry((tile_pos.0, tile_pos.1)).or_insert(0) += 1;
+    }
 
-pub fn spawn_blobs(grid: &Grid, world: &mut World) -> Result<(), Error> {
     let mut spawns = Vec::new();
     for (tile_pos, team, mut spawner) in
         world.with_components::<(TilePosition, PadTeam, BlobSpawn)>()
     {
-        spawner.timer -= 1;
+        spawner.timer = (spawner.timer - 1).max(0);
         if spawner.timer == 0 {
-            spawns.push((tile_pos.0, tile_pos.1, *team));
+            let note = if spawner.chart.is_empty() {
+                Note::new(1)
+            } else {
+                spawner.chart[spawner.chart_index]
+            };
+            // the blob waits to spawn until there is room for it, trying again every beat
+            let crowd = crowds.get(&(tile_pos.0, tile_pos.1)).cloned().unwrap_or(0);
+            if note.length > 0 && grid.tile_capacity().is_some_and(|c| crowd >= c) {
+                continue;
+            }
             spawner.timer = spawner.interval;
+            spawner.chart_index = (spawner.chart_index + 1) % spawner.chart.len().max(1);
+            if note.length > 0 {
+                spawns.push((tile_pos.0, tile_pos.1, *team, note));
+            }
         }
     }
 
-    for (x, y, team) in spawns {
-        let goal_pos = world
-            .with_components::<(TilePosition, PadTeam, BlobGoal)>()
-            .filter(|(_, t, _)| **t == team)
-            .next()
-            .map(|(pos, _, _)| (pos.0, pos.1))
-            .unwrap();
-        let path = grid.find_path((x, y), goal_pos).unwrap();
-        create_blob(world, x, y, team, path)?;
+    let first_index = world
+        .with_components::<(EntityId, Blob)>()
+        .map(|(_, blob)| blob.spawn_index + 1)
+        .max()
+        .unwrap_or(0);
+    for (spawn_index, (x, y, team, note)) in (first_index..).zip(spawns) {
+        // blobs with no way to their goal wait on the spawn until there is one
+        let path = find_goal(team, world)
+            .and_then(|goal| grid.flow_path((x, y), goal))
+            .unwrap_or_else(|| vec![(x, y)]);
+        let blob = Blob {
+            spawn_index,
+            path_index: 0,
+            path,
+            held: 0,
+            waited: false,
+            bumped: false,
+            visibility: 1.0,
+            length: note.length,
+            hold: None,
+            teams: TeamSet::of(&[team]).union(note.chord),
+        };
+        create_blob(world, (x, y), team, theme.blob_radius, sprite, animation, blob)?;
     }
     Ok(())
 }
 
-const BLOB_RADIUS: f32 = 15.0;
+/// Tile of the goal blobs of `team` head for.
+pub fn find_goal(team: PadTeam, world: &World) -> Option<(i32, i32)> {
+    world
+        .with_components::<(TilePosition, PadTeam, BlobGoal)>()
+        .find(|(_, t, _)| **t == team)
+        .map(|(pos, _, _)| (pos.0, pos.1))
+}
+
 fn create_blob(
     world: &mut World,
-    x: i32,
-    y: i32,
+    (x, y): (i32, i32),
     team: PadTeam,
-    path: Vec<(i32, i32)>,
+    radius: f32,
+    sprite: Option<&Sprite>,
+    animation: Option<&Animation>,
+    blob: Blob,
 ) -> Result<(), Error> {
-    world
+    let entity = world
         .add_entity()
         .insert(Position(Vec2::zero()))
         .insert(TilePosition(x, y))
-        .insert(ColoredCircle {
-            radius: BLOB_RADIUS,
+        .insert(RenderLayer::Blobs)
+        .insert(team)
+        .insert(blob);
+    let entity = match sprite {
+        Some(sprite) => entity.insert(sprite.clone()),
+        None => entity.insert(ColoredCircle {
+            radius,
             color: team.color(),
             fill: FillMode::Filled,
-        })
-        .insert(team)
-        .insert(Blob {
-            path_index: 0,
-            path,
-        });
+        }),
+    };
+    if let Some(animation) = animation {
+        entity.insert(animation.clone());
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::{EntityId, World};
+
+    use components::{Blob, BlobGoal, BlobSpawn, PadTeam, TilePosition};
+    use grid::Grid;
+    use systems::spawn_blobs;
+    use theme::Theme;
+
+    fn blobs(world: &World) -> usize {
+        world.with_components::<(EntityId, Blob)>().count()
+    }
+
+    #[test]
+    fn spawns_wait_for_room_on_their_tile() {
+        let (mut grid, mut world) = (Grid::new(3, 3, 10, 10), World::new());
+        grid.set_tile_capacity(Some(1));
+        world
+            .add_entity()
+            .insert(TilePosition(1, 0))
+            .insert(PadTeam::Red)
+            .insert(BlobGoal);
+        world
+            .add_entity()
+            .insert(TilePosition(1, 2))
+            .insert(PadTeam::Red)
+            .insert(BlobSpawn {
+                interval: 1,
+                timer: 1,
+                chart: vec![],
+                chart_index: 0,
+            });
+        let theme = Theme::default();
+
+        spawn_blobs(&grid, &theme, None, None, &mut world).unwrap();
+        assert_eq!(blobs(&world), 1);
+        spawn_blobs(&grid, &theme, None, None, &mut world).unwrap();
+        assert_eq!(blobs(&world), 1);
+
+        // the waiting blob spawns as soon as the first one has moved on
+        for (_, mut tile) in world.with_components::<(Blob, TilePosition)>() {
+            *tile = TilePosition(1, 1);
+        }
+        spawn_blobs(&grid, &theme, None, None, &mut world).unwrap();
+        assert_eq!(blobs(&world), 2);
+    }
+}
@@ -1,13 +1,33 @@
+mod animate_sprites;
+mod apply_theme;
 mod blob_move;
 mod blob_spawn;
 mod grid_positioning;
 mod pad_update;
+mod render_chord_blobs;
+mod render_hit_areas;
+mod render_long_blobs;
 mod render_primitives;
+mod render_selected_pads;
+mod render_sprites;
+mod render_team_shapes;
+mod stack_blobs;
+mod team_colors;
 mod trigger_blobs;
 
+pub use self::animate_sprites::*;
+pub use self::apply_theme::*;
 pub use self::blob_move::*;
 pub use self::blob_spawn::*;
 pub use self::grid_positioning::*;
 pub use self::pad_update::*;
+pub use self::render_chord_blobs::*;
+pub use self::render_hit_areas::*;
+pub use self::render_long_blobs::*;
 pub use self::render_primitives::*;
+pub use self::render_selected_pads::*;
+pub use self::render_sprites::*;
+pub use self::render_team_shapes::*;
+pub use self::stack_blobs::*;
+pub use self::team_colors::*;
 pub use self::trigger_blobs::*;
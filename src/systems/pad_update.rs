@@ -1,39 +1,115 @@
-use embla::ecs::World;
-use embla::input::{Input, Key};
+use embla::ecs::{EntityId, World};
 use failure::Error;
 
-use components::{ColoredCircle, Pad, PadTeam, TilePosition, TileTrigger};
+use controls::{Command, Controls};
+use components::{Animation, AnimationState, ColoredCircle, FillMode, Pad, PadMovement,
+                 PadTeam, TilePosition, TileTrigger};
 use grid::Grid;
+use terrain::Direction;
+use theme::Theme;
 
 const PAD_PULSE_TIME: f32 = 0.1;
-pub fn pad_update(dt: f32, input: &Input, grid: &Grid, world: &mut World) -> Result<(), Error> {
+/// How long the tiles reached by a hit stay highlighted.
+pub const HIT_HIGHLIGHT_TIME: f32 = 0.25;
+
+/// Hits and moves pads. Moves and cooldowns wait for `on_beat`, a pad that is hit on the same
+/// frame it moves triggers around its new tile.
+pub fn pad_update(
+    dt: f32,
+    on_beat: bool,
+    controls: &Controls,
+    grid: &Grid,
+    theme: &Theme,
+    world: &mut World,
+) -> Result<(), Error> {
+    // the move keys switch to the last pad hit
+    let selected_team = controls.commands.iter().rev().find_map(|&command| match command {
+        Command::HitPad(team) => Some(team),
+        _ => None,
+    });
+    let moves: Vec<Direction> = controls
+        .commands
+        .iter()
+        .filter_map(|&command| match command {
+            Command::MovePad(direction) => Some(direction),
+            _ => None,
+        })
+        .collect();
+    let mut pad_tiles: Vec<TilePosition> = world
+        .with_components::<(Pad, TilePosition)>()
+        .map(|(_, tile_pos)| *tile_pos)
+        .collect();
+
     let mut triggered_tiles = Vec::new();
-    for (mut circle, mut pad, team, tile_pos) in
-        world.with_components::<(ColoredCircle, Pad, PadTeam, TilePosition)>()
+    let mut triggered_pads = Vec::new();
+    for (e, mut pad, team, mut tile_pos) in
+        world.with_components::<(EntityId, Pad, PadTeam, TilePosition)>()
     {
         pad.pulse_timer = (pad.pulse_timer - dt).max(0.0);
+        pad.highlight_timer = (pad.highlight_timer - dt).max(0.0);
+
+        if let Some(selected_team) = selected_team {
+            pad.selected = *team == selected_team;
+        }
+        if pad.selected {
+            if let Some(&direction) = moves.iter().rev().find(|&&d| pad.movement.allows(d)) {
+                pad.queued_move = Some(direction);
+            }
+        }
+        if on_beat {
+            pad.cooldown_left = (pad.cooldown_left - 1).max(0);
+            if let Some(direction) = pad.queued_move.take() {
+                let offset = direction.offset();
+                let target = TilePosition(tile_pos.0 + offset.0, tile_pos.1 + offset.1);
+                // pads can't move onto walls or each other
+                if !grid.blocked((target.0, target.1)) && !pad_tiles.contains(&target) {
+                    pad_tiles.retain(|&t| t != *tile_pos);
+                    pad_tiles.push(target);
+                    *tile_pos = target;
+                }
+            }
+        }
 
         let mut trigger = |pad: &mut Pad| {
-            triggered_tiles.push((*tile_pos, *team));
+            triggered_tiles.push((*tile_pos, *team, pad.hit_shape));
+            triggered_pads.push(e.0);
             pad.pulse_timer = PAD_PULSE_TIME;
+            pad.highlight_timer = HIT_HIGHLIGHT_TIME;
+            pad.hit_tile = Some((tile_pos.0, tile_pos.1));
+            pad.cooldown_left = pad.cooldown;
         };
 
-        match *team {
-            PadTeam::Blue if input.key_is_pressed(&Key::A) => trigger(&mut pad),
-            PadTeam::Red if input.key_is_pressed(&Key::S) => trigger(&mut pad),
-            PadTeam::Green if input.key_is_pressed(&Key::D) => trigger(&mut pad),
-            PadTeam::Yellow if input.key_is_pressed(&Key::F) => trigger(&mut pad),
-            _ => {}
+        if controls.pressed(Command::HitPad(*team)) && pad.cooldown_left == 0 {
+            trigger(&mut pad);
         }
+    }
 
-        let max_size = grid.cell_height() as f32 * 0.35;
-        let min_size = grid.cell_height() as f32 * 0.45;
+    for (mut circle, pad) in world.with_components::<(ColoredCircle, Pad)>() {
+        let max_size = grid.cell_height() * theme.pad_pulse_radius;
+        let min_size = grid.cell_height() * theme.pad_radius;
         let r = pad.pulse_timer / PAD_PULSE_TIME;
         circle.radius = min_size + (r * (max_size - min_size));
+        circle.fill = if pad.selected && pad.movement != PadMovement::Fixed {
+            FillMode::Outline(theme.pad_selected_outline)
+        } else {
+            FillMode::Outline(theme.pad_outline)
+        };
+    }
+
+    for (e, _, mut animation) in world.with_components::<(EntityId, Pad, Animation)>() {
+        if triggered_pads.contains(&e.0) && animation.has_clip(AnimationState::Hit) {
+            // restart the hit animation on every press
+            animation.set_state(AnimationState::Idle);
+            animation.set_state(AnimationState::Hit);
+        }
     }
 
-    for (tile, team) in triggered_tiles {
-        world.add_entity().insert(TileTrigger { team }).insert(tile);
+    for (tile, team, shape) in triggered_tiles {
+        world.add_entity().insert(TileTrigger {
+            team,
+            shape,
+            age: 0.0,
+        }).insert(tile);
     }
 
     Ok(())
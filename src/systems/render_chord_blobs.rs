@@ -0,0 +1,138 @@
+use std::f32::consts::PI;
+
+use embla::ecs::World;
+use embla::math::Vec2;
+use failure::Error;
+
+use components::{Blob, ColoredCircle, Position, RenderLayer, Sprite};
+use palette::Palette;
+use render_interface::RenderInterface;
+use theme::Theme;
+
+// points along the edge of each team's slice
+const SLICE_SEGMENTS: i32 = 8;
+// width of the ring of slices around sprite blobs, relative to their radius
+const SPRITE_RING: f32 = 0.3;
+
+/// Splits blobs belonging to several teams into a slice in each team's color. Slices are drawn
+/// over the circle of blobs drawn as circles, and as a ring around blobs drawn as sprites so the
+/// sprite stays visible.
+pub fn render_chord_blobs(
+    palette: Palette,
+    theme: &Theme,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    renderer.set_layer(RenderLayer::Blobs);
+    for (position, blob, circle) in world.with_components::<(Position, Blob, ColoredCircle)>() {
+        draw_slices(renderer, palette, &blob, position.0, 0.0, circle.radius)?;
+    }
+    for (position, blob, _) in world.with_components::<(Position, Blob, Sprite)>() {
+        let radius = theme.blob_radius;
+        let outer = radius * (1.0 + SPRITE_RING);
+        draw_slices(renderer, palette, &blob, position.0, radius, outer)?;
+    }
+
+    Ok(())
+}
+
+/// Draws a slice of the ring between `inner` and `outer` for each of the blob's teams, a whole
+/// pie if `inner` is 0.
+fn draw_slices(
+    renderer: &mut RenderInterface,
+    palette: Palette,
+    blob: &Blob,
+    center: Vec2,
+    inner: f32,
+    outer: f32,
+) -> Result<(), Error> {
+    let teams = blob.teams.teams();
+    if teams.len() < 2 {
+        return Ok(());
+    }
+
+    let slice = 2.0 * PI / teams.len() as f32;
+    for (i, &team) in teams.iter().enumerate() {
+        let mut color = palette.team_color(team);
+        color.3 *= blob.visibility;
+
+        let angle = |segment: i32| {
+            PI / 2.0 + slice * (i as f32 + segment as f32 / SLICE_SEGMENTS as f32)
+        };
+        if inner <= 0.0 {
+            let mut points = vec![center];
+            for segment in 0..SLICE_SEGMENTS + 1 {
+                points.push(center + Vec2::with_angle(angle(segment)) * outer);
+            }
+            renderer.draw_polygon(&points, color)?;
+        } else {
+            // a ring slice isn't convex, so it's drawn a segment at a time
+            for segment in 0..SLICE_SEGMENTS {
+                let a = Vec2::with_angle(angle(segment));
+                let b = Vec2::with_angle(angle(segment + 1));
+                let points = [
+                    center + a * inner,
+                    center + a * outer,
+                    center + b * outer,
+                    center + b * inner,
+                ];
+                renderer.draw_polygon(&points, color)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::World;
+    use embla::math::Vec2;
+
+    use components::{Blob, PadTeam, Position, Sprite, TeamSet};
+    use palette::Palette;
+    use recording_renderer::RecordingRenderer;
+    use systems::render_chord_blobs;
+    use theme::Theme;
+
+    #[test]
+    fn sprite_chords_are_ringed_with_slices() {
+        let mut world = World::new();
+        world
+            .add_entity()
+            .insert(Position(Vec2::new(50.0, 50.0)))
+            .insert(PadTeam::Red)
+            .insert(Blob {
+                spawn_index: 0,
+                path_index: 0,
+                path: vec![(0, 0)],
+                held: 0,
+                waited: false,
+                bumped: false,
+                visibility: 1.0,
+                length: 1,
+                hold: None,
+                teams: TeamSet::of(&[PadTeam::Red, PadTeam::Green]),
+            })
+            .insert(Sprite {
+                image: "blob.png".to_string(),
+                frame_size: None,
+                frame: 0,
+                tint: (1.0, 1.0, 1.0, 1.0),
+                scale: 1.0,
+                rotation: 0.0,
+            });
+        let theme = Theme {
+            blob_radius: 10.0,
+            ..Theme::default()
+        };
+        let mut renderer = RecordingRenderer::new((100, 100));
+        render_chord_blobs(Palette::Default, &theme, &mut world, &mut renderer).unwrap();
+
+        for &team in &[PadTeam::Red, PadTeam::Green] {
+            let color = Palette::Default.team_color(team);
+            let slice = renderer.find(|d| d.command.has_color(color));
+            assert!(!slice.is_empty());
+        }
+    }
+}
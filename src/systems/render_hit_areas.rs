@@ -0,0 +1,79 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Pad, PadTeam, RenderLayer};
+use grid::Grid;
+use palette::Palette;
+use render_interface::{BlendMode, RenderInterface};
+use systems::HIT_HIGHLIGHT_TIME;
+use theme::Theme;
+
+/// Highlights the tiles reached by recent pad hits, fading out over `HIT_HIGHLIGHT_TIME`.
+pub fn render_hit_areas(
+    grid: &Grid,
+    palette: Palette,
+    theme: &Theme,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    renderer.set_layer(RenderLayer::Board);
+    // highlights light up the tiles instead of covering them
+    renderer.set_blend_mode(BlendMode::Additive);
+    for (pad, team) in world.with_components::<(Pad, PadTeam)>() {
+        let center = match pad.hit_tile {
+            Some(tile) if pad.highlight_timer > 0.0 => tile,
+            _ => continue,
+        };
+        let mut color = palette.team_color(*team);
+        color.3 *= theme.hit_highlight_alpha * pad.highlight_timer / HIT_HIGHLIGHT_TIME;
+
+        for x in 0..grid.width() {
+            for y in 0..grid.height() {
+                if pad.hit_shape.covers(grid, center, (x, y)) {
+                    let outline = grid.cell_outline((x, y), theme.grid_gap);
+                    renderer.draw_polygon(&outline, color)?;
+                }
+            }
+        }
+    }
+    renderer.set_blend_mode(BlendMode::Alpha);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::World;
+
+    use components::{HitShape, Pad, PadMovement, PadTeam, TilePosition};
+    use grid::Grid;
+    use palette::Palette;
+    use recording_renderer::RecordingRenderer;
+    use systems::render_hit_areas;
+    use theme::Theme;
+
+    #[test]
+    fn highlights_stay_where_the_hit_was() {
+        let grid = Grid::new(3, 3, 20, 20);
+        let mut world = World::new();
+        let mut pad = Pad::new(PadMovement::Row, HitShape::Tile, 0);
+        pad.highlight_timer = 0.1;
+        pad.hit_tile = Some((0, 1));
+        world
+            .add_entity()
+            .insert(TilePosition(1, 1))
+            .insert(PadTeam::Red)
+            .insert(pad);
+        let mut renderer = RecordingRenderer::new((100, 100));
+        render_hit_areas(
+            &grid,
+            Palette::Default,
+            &Theme::default(),
+            &mut world,
+            &mut renderer,
+        ).unwrap();
+
+        renderer.assert_tile_count(&grid, (0, 1), 1, "highlight", |_| true);
+        assert_eq!(renderer.draws().len(), 1);
+    }
+}
@@ -0,0 +1,61 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Blob, PadTeam, Position, RenderLayer};
+use grid::Grid;
+use palette::Palette;
+use render_interface::RenderInterface;
+use theme::Theme;
+
+/// Draws the tails of long blobs as capsules trailing behind them along their path. Tails shrink
+/// while the blob is held and stop at teleporters and the start of the path.
+pub fn render_long_blobs(
+    grid: &Grid,
+    palette: Palette,
+    theme: &Theme,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    renderer.set_layer(RenderLayer::Blobs);
+    for (position, blob, team) in world.with_components::<(Position, Blob, PadTeam)>() {
+        let tail = blob.length as f32 - blob.hold.unwrap_or(0.0) - 1.0;
+        if tail <= 0.0 {
+            continue;
+        }
+        let mut color = palette.team_color(*team);
+        color.3 *= blob.visibility;
+
+        // tiles behind the blob, skipping the one it is on if it hasn't got there yet
+        let mut behind = blob.path[..blob.path_index + 1].iter().rev();
+        if blob.path_index > 0 {
+            let here = grid.cell_center(blob.path[blob.path_index]);
+            let prev = grid.cell_center(blob.path[blob.path_index - 1]);
+            let (to_prev, to_blob) = (prev - here, position.0 - here);
+            if to_prev.0 * to_blob.0 + to_prev.1 * to_blob.1 > 0.0 {
+                behind.next();
+            }
+        }
+
+        let mut left = tail * grid.cell_width();
+        let mut from = position.0;
+        let mut from_tile = None;
+        for &tile in behind {
+            if left <= 0.0 || from_tile.and_then(|t| grid.teleporter(&t)) == Some(tile) {
+                break;
+            }
+            let to = grid.cell_center(tile);
+            let length = (to - from).mag();
+            let to = if length > left {
+                from + (to - from) * (left / length)
+            } else {
+                to
+            };
+            renderer.draw_capsule(from, to, theme.long_blob_radius, color)?;
+            left -= length;
+            from = to;
+            from_tile = Some(tile);
+        }
+    }
+
+    Ok(())
+}
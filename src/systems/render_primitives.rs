@@ -1,43 +1,106 @@
-use std;
-
 use embla::ecs::World;
-use embla::math::Vec2;
 use failure::Error;
 
-use components::{ColoredCircle, ColoredRect, FillMode, Position};
+use components::{ColoredCircle, ColoredPolygon, FillMode, Position, RenderLayer};
 use render_interface::RenderInterface;
 
 pub fn render_primitives(world: &mut World, renderer: &mut RenderInterface) -> Result<(), Error> {
-    for (position, r) in world.with_components::<(Position, ColoredRect)>() {
-        let size = Vec2::new((r.rect.2 - r.rect.0) as f32, (r.rect.3 - r.rect.1) as f32);
-        let pos = position.0 - size / 2.0;
-        let rect = (
-            r.rect.0 as f32 + pos.0,
-            r.rect.1 as f32 + pos.1,
-            r.rect.2 as f32 + pos.0,
-            r.rect.2 as f32 + pos.1,
-        );
-        renderer.draw_rect(rect, r.color)?;
+    // entities without a layer would silently never be drawn
+    debug_assert_eq!(
+        world.with_components::<(Position, ColoredPolygon)>().count(),
+        world.with_components::<(Position, ColoredPolygon, RenderLayer)>().count(),
+        "a ColoredPolygon entity has no RenderLayer"
+    );
+    debug_assert_eq!(
+        world.with_components::<(Position, ColoredCircle)>().count(),
+        world.with_components::<(Position, ColoredCircle, RenderLayer)>().count(),
+        "a ColoredCircle entity has no RenderLayer"
+    );
+
+    for (position, p, layer) in world.with_components::<(Position, ColoredPolygon, RenderLayer)>() {
+        renderer.set_layer(*layer);
+        let points = p.points.iter().map(|&point| point + position.0).collect::<Vec<_>>();
+        renderer.draw_polygon(&points, p.color)?;
     }
 
-    for (position, c) in world.with_components::<(Position, ColoredCircle)>() {
+    for (position, c, layer) in world.with_components::<(Position, ColoredCircle, RenderLayer)>() {
+        renderer.set_layer(*layer);
         if c.fill == FillMode::Filled {
-            renderer.draw_circle(position.0, c.radius, 20, c.color)?;
+            renderer.draw_circle(position.0, c.radius, c.color)?;
         } else if let FillMode::Outline(width) = c.fill {
-            let num_points = 20;
-            let points = (0..num_points)
-                .map(|i| {
-                    let a = i as f32 * (std::f32::consts::PI * 2.0 / num_points as f32);
-                    position.0 + Vec2::with_angle(a) * c.radius
-                })
-                .collect::<Vec<_>>();
-            let mut other_points = points.iter().skip(1).cloned().collect::<Vec<_>>();
-            other_points.push(points[0]);
-            for (p1, p2) in points.into_iter().zip(other_points.into_iter()) {
-                renderer.draw_line(p1, p2, width, c.color)?;
-            }
+            // centered on the radius like a stroked path
+            renderer.draw_ring(position.0, c.radius + width / 2.0, width, c.color)?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::World;
+    use embla::math::Vec2;
+
+    use components::{ColoredCircle, ColoredPolygon, FillMode, PadTeam, Position, RenderLayer};
+    use recording_renderer::RecordingRenderer;
+    use systems::render_primitives;
+
+    const RED: (f32, f32, f32, f32) = (1.0, 0.0, 0.0, 1.0);
+    const GREEN: (f32, f32, f32, f32) = (0.0, 1.0, 0.0, 1.0);
+
+    fn circle(world: &mut World, position: Vec2, fill: FillMode, color: (f32, f32, f32, f32)) {
+        world
+            .add_entity()
+            .insert(Position(position))
+            .insert(RenderLayer::Blobs)
+            .insert(PadTeam::Red)
+            .insert(ColoredCircle {
+                radius: 10.0,
+                color,
+                fill,
+            });
+    }
+
+    #[test]
+    fn circles_are_drawn_filled_or_as_rings() {
+        let mut world = World::new();
+        circle(&mut world, Vec2::new(20.0, 20.0), FillMode::Filled, RED);
+        circle(&mut world, Vec2::new(60.0, 20.0), FillMode::Outline(4.0), GREEN);
+        let mut renderer = RecordingRenderer::new((100, 100));
+        render_primitives(&mut world, &mut renderer).unwrap();
+
+        renderer.assert_count(1, "red filled circle", |d| {
+            d.command.is_filled_circle() && d.command.is_at(Vec2::new(20.0, 20.0))
+                && d.command.has_color(RED) && d.layer == RenderLayer::Blobs
+        });
+        renderer.assert_count(1, "green ring", |d| {
+            d.command.is_ring() && d.command.is_at(Vec2::new(60.0, 20.0))
+                && d.command.has_color(GREEN)
+        });
+        assert_eq!(renderer.draws().len(), 2);
+    }
+
+    #[test]
+    fn polygons_are_drawn_around_their_position() {
+        let mut world = World::new();
+        world
+            .add_entity()
+            .insert(Position(Vec2::new(50.0, 40.0)))
+            .insert(RenderLayer::Walls)
+            .insert(ColoredPolygon {
+                points: vec![
+                    Vec2::new(-10.0, -5.0),
+                    Vec2::new(10.0, -5.0),
+                    Vec2::new(10.0, 5.0),
+                    Vec2::new(-10.0, 5.0),
+                ],
+                color: RED,
+            });
+        let mut renderer = RecordingRenderer::new((100, 100));
+        render_primitives(&mut world, &mut renderer).unwrap();
+
+        renderer.assert_count(1, "wall polygon", |d| {
+            d.command.is_at(Vec2::new(50.0, 40.0)) && d.layer == RenderLayer::Walls
+        });
+    }
+}
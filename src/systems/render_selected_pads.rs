@@ -0,0 +1,84 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Pad, PadMovement, PadTeam, Position, RenderLayer, Sprite};
+use grid::Grid;
+use palette::Palette;
+use render_interface::RenderInterface;
+use theme::Theme;
+
+/// Rings the pad the move keys control when it is drawn as a sprite, pads drawn as circles show
+/// it with a thicker outline instead.
+pub fn render_selected_pads(
+    grid: &Grid,
+    palette: Palette,
+    theme: &Theme,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    renderer.set_layer(RenderLayer::Markers);
+    for (position, pad, team, _) in world.with_components::<(Position, Pad, PadTeam, Sprite)>() {
+        if !pad.selected || pad.movement == PadMovement::Fixed {
+            continue;
+        }
+        let radius = grid.cell_height() * theme.pad_radius;
+        let width = theme.pad_selected_outline;
+        let color = palette.team_color(*team);
+        renderer.draw_ring(position.0, radius + width / 2.0, width, color)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::World;
+    use embla::math::Vec2;
+
+    use components::{HitShape, Pad, PadMovement, PadTeam, Position, Sprite};
+    use grid::Grid;
+    use palette::Palette;
+    use recording_renderer::RecordingRenderer;
+    use systems::render_selected_pads;
+    use theme::Theme;
+
+    fn sprite_pad(world: &mut World, position: Vec2, movement: PadMovement, selected: bool) {
+        let mut pad = Pad::new(movement, HitShape::Tile, 0);
+        pad.selected = selected;
+        world
+            .add_entity()
+            .insert(Position(position))
+            .insert(PadTeam::Red)
+            .insert(pad)
+            .insert(Sprite {
+                image: "pad.png".to_string(),
+                frame_size: None,
+                frame: 0,
+                tint: (1.0, 1.0, 1.0, 1.0),
+                scale: 1.0,
+                rotation: 0.0,
+            });
+    }
+
+    #[test]
+    fn only_selected_movable_pads_are_ringed() {
+        let mut world = World::new();
+        sprite_pad(&mut world, Vec2::new(10.0, 10.0), PadMovement::Row, true);
+        sprite_pad(&mut world, Vec2::new(30.0, 10.0), PadMovement::Row, false);
+        sprite_pad(&mut world, Vec2::new(50.0, 10.0), PadMovement::Fixed, true);
+        let grid = Grid::new(3, 3, 20, 20);
+        let mut renderer = RecordingRenderer::new((100, 100));
+        render_selected_pads(
+            &grid,
+            Palette::Default,
+            &Theme::default(),
+            &mut world,
+            &mut renderer,
+        ).unwrap();
+
+        renderer.assert_count(1, "selection ring", |d| {
+            d.command.is_ring() && d.command.is_at(Vec2::new(10.0, 10.0))
+        });
+        assert_eq!(renderer.draws().len(), 1);
+    }
+}
@@ -0,0 +1,37 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Position, RenderLayer, Sprite};
+use render_interface::RenderInterface;
+use sprites::Sprites;
+
+pub fn render_sprites(
+    sprites: &Sprites,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    // entities without a layer would silently never be drawn
+    debug_assert_eq!(
+        world.with_components::<(Position, Sprite)>().count(),
+        world.with_components::<(Position, Sprite, RenderLayer)>().count(),
+        "a Sprite entity has no RenderLayer"
+    );
+
+    for (position, sprite, layer) in world.with_components::<(Position, Sprite, RenderLayer)>() {
+        let frames = match sprites.frames(&sprite.image, sprite.frame_size) {
+            Some(frames) => frames,
+            None => continue,
+        };
+        let texture = &frames[sprite.frame % frames.len()];
+        renderer.set_layer(*layer);
+        renderer.draw_texture(
+            texture,
+            position.0,
+            sprite.scale,
+            sprite.rotation,
+            sprite.tint,
+        )?;
+    }
+
+    Ok(())
+}
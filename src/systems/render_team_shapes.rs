@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use embla::ecs::{EntityId, World};
+use embla::math::Vec2;
+use failure::Error;
+
+use components::{Blob, ColoredCircle, FillMode, PadTeam, Position, RenderLayer, Sprite, TeamSet};
+use grid::Grid;
+use render_interface::RenderInterface;
+use theme::Theme;
+
+// dark marker drawn on top of filled circles and sprites so it shows up on them
+const MARKER_COLOR: (f32, f32, f32, f32) = (0.0, 0.0, 0.0, 0.8);
+
+/// Draws a distinct shape for each team on everything that belongs to a team, so teams can be
+/// told apart without relying on color. Chord blobs get a smaller shape for each of their teams,
+/// placed on the team's slice.
+pub fn render_team_shapes(
+    grid: &Grid,
+    theme: &Theme,
+    world: &mut World,
+    renderer: &mut RenderInterface,
+) -> Result<(), Error> {
+    let mut teams = HashMap::new();
+    for (e, team) in world.with_components::<(EntityId, PadTeam)>() {
+        teams.insert(e.0, TeamSet::of(&[*team]));
+    }
+    for (e, blob) in world.with_components::<(EntityId, Blob)>() {
+        teams.insert(e.0, blob.teams);
+    }
+
+    for (e, position, c, layer) in
+        world.with_components::<(EntityId, Position, ColoredCircle, RenderLayer)>()
+    {
+        let teams = match teams.get(&e.0) {
+            Some(teams) => teams,
+            None => continue,
+        };
+        let color = if c.fill == FillMode::Filled {
+            MARKER_COLOR
+        } else {
+            c.color
+        };
+        renderer.set_layer(*layer);
+        draw_team_shapes(renderer, teams, position.0, c.radius, color)?;
+    }
+
+    let blobs = world
+        .with_components::<(EntityId, Blob)>()
+        .map(|(e, _)| e.0)
+        .collect::<Vec<_>>();
+    for (e, position, _, layer) in
+        world.with_components::<(EntityId, Position, Sprite, RenderLayer)>()
+    {
+        let teams = match teams.get(&e.0) {
+            Some(teams) => teams,
+            None => continue,
+        };
+        // only blobs and pads are drawn as sprites
+        let radius = if blobs.contains(&e.0) {
+            theme.blob_radius
+        } else {
+            grid.cell_width() * theme.pad_radius
+        };
+        renderer.set_layer(*layer);
+        draw_team_shapes(renderer, teams, position.0, radius, MARKER_COLOR)?;
+    }
+
+    Ok(())
+}
+
+/// Draws the shapes of `teams` inside a circle of `radius`.
+fn draw_team_shapes(
+    renderer: &mut RenderInterface,
+    teams: &TeamSet,
+    center: Vec2,
+    radius: f32,
+    color: (f32, f32, f32, f32),
+) -> Result<(), Error> {
+    let teams = teams.teams();
+    if teams.len() < 2 {
+        for &team in &teams {
+            draw_team_shape(renderer, team, center, radius * 0.5, color)?;
+        }
+        return Ok(());
+    }
+
+    // in the middle of the team's slice, see `render_chord_blobs`
+    let slice = 2.0 * PI / teams.len() as f32;
+    for (i, &team) in teams.iter().enumerate() {
+        let angle = PI / 2.0 + slice * (i as f32 + 0.5);
+        let position = center + Vec2::with_angle(angle) * radius * 0.5;
+        draw_team_shape(renderer, team, position, radius * 0.25, color)?;
+    }
+
+    Ok(())
+}
+
+/// Draws a distinct shape for each team so they can be told apart without relying on color.
+fn draw_team_shape(
+    renderer: &mut RenderInterface,
+    team: PadTeam,
+    center: Vec2,
+    size: f32,
+    color: (f32, f32, f32, f32),
+) -> Result<(), Error> {
+    let width = 2.0;
+    match team {
+        PadTeam::Blue => draw_outline(renderer, center, size, 3, PI * 0.5, width, color),
+        PadTeam::Red => draw_outline(renderer, center, size, 4, PI * 0.25, width, color),
+        PadTeam::Green => draw_outline(renderer, center, size, 4, 0.0, width, color),
+        PadTeam::Yellow => {
+            renderer.draw_line(
+                center - Vec2::new(size, 0.0),
+                center + Vec2::new(size, 0.0),
+                width,
+                color,
+            )?;
+            renderer.draw_line(
+                center - Vec2::new(0.0, size),
+                center + Vec2::new(0.0, size),
+                width,
+                color,
+            )
+        }
+    }
+}
+
+/// Draws the outline of a regular polygon.
+fn draw_outline(
+    renderer: &mut RenderInterface,
+    center: Vec2,
+    radius: f32,
+    num_points: i32,
+    rotation: f32,
+    width: f32,
+    color: (f32, f32, f32, f32),
+) -> Result<(), Error> {
+    let points = (0..num_points)
+        .map(|i| {
+            let a = rotation + i as f32 * (PI * 2.0 / num_points as f32);
+            center + Vec2::with_angle(a) * radius
+        })
+        .collect::<Vec<_>>();
+    let mut other_points = points.iter().skip(1).cloned().collect::<Vec<_>>();
+    other_points.push(points[0]);
+    for (p1, p2) in points.into_iter().zip(other_points) {
+        renderer.draw_capsule(p1, p2, width / 2.0, color)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::ecs::World;
+    use embla::math::Vec2;
+
+    use components::{Blob, ColoredCircle, FillMode, HitShape, Pad, PadMovement, PadTeam,
+                     Position, RenderLayer, Sprite, TeamSet};
+    use grid::Grid;
+    use recording_renderer::RecordingRenderer;
+    use systems::render_team_shapes;
+    use theme::Theme;
+
+    const MARKER: (f32, f32, f32, f32) = (0.0, 0.0, 0.0, 0.8);
+
+    fn sprite() -> Sprite {
+        Sprite {
+            image: "sprite.png".to_string(),
+            frame_size: None,
+            frame: 0,
+            tint: (1.0, 1.0, 1.0, 1.0),
+            scale: 1.0,
+            rotation: 0.0,
+        }
+    }
+
+    fn blob(teams: &[PadTeam]) -> Blob {
+        Blob {
+            spawn_index: 0,
+            path_index: 0,
+            path: vec![(0, 0)],
+            held: 0,
+            waited: false,
+            bumped: false,
+            visibility: 1.0,
+            length: 1,
+            hold: None,
+            teams: TeamSet::of(teams),
+        }
+    }
+
+    fn render(world: &mut World) -> RecordingRenderer {
+        let grid = Grid::new(4, 4, 40, 40);
+        let mut renderer = RecordingRenderer::new((200, 200));
+        render_team_shapes(&grid, &Theme::default(), world, &mut renderer).unwrap();
+        renderer
+    }
+
+    #[test]
+    fn team_shapes_are_drawn_on_top_of_team_circles() {
+        let mut world = World::new();
+        world
+            .add_entity()
+            .insert(Position(Vec2::new(20.0, 20.0)))
+            .insert(RenderLayer::Blobs)
+            .insert(PadTeam::Red)
+            .insert(ColoredCircle {
+                radius: 10.0,
+                color: (1.0, 0.0, 0.0, 1.0),
+                fill: FillMode::Filled,
+            });
+        let renderer = render(&mut world);
+
+        // red is a square drawn as four capsules, dark so it shows on the filled circle
+        assert_eq!(renderer.draws().len(), 4);
+        assert!(renderer.draws().iter().all(|d| d.command.has_color(MARKER)));
+    }
+
+    #[test]
+    fn sprite_pads_get_their_team_shape() {
+        let mut world = World::new();
+        world
+            .add_entity()
+            .insert(Position(Vec2::new(60.0, 60.0)))
+            .insert(RenderLayer::Markers)
+            .insert(PadTeam::Blue)
+            .insert(Pad::new(PadMovement::Fixed, HitShape::Tile, 0))
+            .insert(sprite());
+        let renderer = render(&mut world);
+
+        // blue is a triangle
+        renderer.assert_count(3, "triangle side", |d| {
+            d.command.has_color(MARKER) && d.layer == RenderLayer::Markers
+        });
+        assert_eq!(renderer.draws().len(), 3);
+    }
+
+    #[test]
+    fn chord_blobs_get_a_shape_for_each_team() {
+        let mut world = World::new();
+        world
+            .add_entity()
+            .insert(Position(Vec2::new(100.0, 100.0)))
+            .insert(RenderLayer::Blobs)
+            .insert(PadTeam::Blue)
+            .insert(blob(&[PadTeam::Blue, PadTeam::Yellow]))
+            .insert(sprite());
+        let renderer = render(&mut world);
+
+        // a triangle for blue and a cross for yellow, on either side of the blob
+        let blue = renderer.find(|d| d.command.center().0 < 100.0);
+        let yellow = renderer.find(|d| d.command.center().0 > 100.0);
+        assert_eq!(blue.len(), 3);
+        assert_eq!(yellow.len(), 2);
+    }
+}
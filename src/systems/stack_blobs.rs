@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use embla::ecs::{EntityId, World};
+use embla::math::Vec2;
+use failure::Error;
+
+use components::{Blob, Position, TilePosition};
+use grid::Grid;
+
+// distance of stacked blobs from the tile center in cells
+const STACK_SPREAD: f32 = 0.2;
+
+/// Spreads out blobs sharing a tile around its center so they don't hide each other.
+pub fn stack_blobs(grid: &Grid, world: &mut World) -> Result<(), Error> {
+    let mut tiles: HashMap<(i32, i32), Vec<(u32, usize)>> = HashMap::new();
+    for (e, tile_pos, blob) in world.with_components::<(EntityId, TilePosition, Blob)>() {
+        tiles
+            .entry((tile_pos.0, tile_pos.1))
+            .or_default()
+            .push((blob.spawn_index, e.0));
+    }
+    for stack in tiles.values_mut() {
+        stack.sort();
+    }
+
+    let spread = grid.cell_width() * STACK_SPREAD;
+    for (e, mut position, tile_pos, _) in
+        world.with_components::<(EntityId, Position, TilePosition, Blob)>()
+    {
+        let stack = &tiles[&(tile_pos.0, tile_pos.1)];
+        if stack.len() < 2 {
+            continue;
+        }
+        let i = stack.iter().position(|&(_, id)| id == e.0).unwrap();
+        let angle = 2.0 * PI * i as f32 / stack.len() as f32;
+        position.0 = position.0 + Vec2::with_angle(angle) * spread;
+    }
+
+    Ok(())
+}
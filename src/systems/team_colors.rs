@@ -0,0 +1,23 @@
+use embla::ecs::World;
+use failure::Error;
+
+use components::{Blob, ColoredCircle, PadTeam, Sprite};
+use palette::Palette;
+
+pub fn team_colors(palette: Palette, world: &mut World) -> Result<(), Error> {
+    for (team, mut circle) in world.with_components::<(PadTeam, ColoredCircle)>() {
+        circle.color = palette.team_color(*team);
+    }
+    for (team, mut sprite) in world.with_components::<(PadTeam, Sprite)>() {
+        sprite.tint = palette.team_color(*team);
+    }
+
+    for (blob, mut circle) in world.with_components::<(Blob, ColoredCircle)>() {
+        circle.color.3 *= blob.visibility;
+    }
+    for (blob, mut sprite) in world.with_components::<(Blob, Sprite)>() {
+        sprite.tint.3 *= blob.visibility;
+    }
+
+    Ok(())
+}
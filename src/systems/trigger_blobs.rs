@@ -1,30 +1,163 @@
 use embla::ecs::{EntityId, World};
 use failure::Error;
 
-use components::{Blob, TilePosition, TileTrigger};
-
-pub fn trigger_blobs(world: &mut World) -> Result<(), Error> {
-    let (trigger_entities, triggered_tiles): (Vec<usize>, Vec<TilePosition>) = world
-        .with_components::<(EntityId, TileTrigger, TilePosition)>()
-        .map(|(e, _, t)| (e.0, *t))
-        .unzip();
-    for e in trigger_entities {
+use components::{Animation, AnimationState, Blob, HitShape, Pad, PadTeam, Position, RenderLayer,
+                 Sprite, TilePosition, TileTrigger};
+use controls::{Command, Controls};
+use grid::Grid;
+
+// beats all the pads of a chord have to be hit within
+const CHORD_WINDOW: f32 = 0.4;
+
+/// Hits blobs on the tiles reached by pads, returns the credit earned for blobs that died. Long
+/// blobs start being held instead and are finished by `hold_blobs`.
+///
+/// A blob is hit by a pad of its team hit this frame once every one of its teams has hit it
+/// within `CHORD_WINDOW`, so single team blobs only need the one press.
+pub fn trigger_blobs(beats: f32, grid: &Grid, world: &mut World) -> Result<f32, Error> {
+    let mut triggers: Vec<(TilePosition, PadTeam, HitShape, bool)> = Vec::new();
+    let mut expired = Vec::new();
+    for (e, mut trigger, t) in world.with_components::<(EntityId, TileTrigger, TilePosition)>() {
+        triggers.push((*t, trigger.team, trigger.shape, trigger.age == 0.0));
+        trigger.age += beats;
+        if trigger.age > CHORD_WINDOW {
+            expired.push(e.0);
+        }
+    }
+    for e in expired {
         world.remove_entity(e);
     }
 
-    let remove: Vec<usize> = world
+    // only blobs of the pad's team are hit, and only the oldest one on each tile when blobs queue
+    // up
+    let mut hit: Vec<(u32, (usize, TilePosition), usize)> = world
         .with_components::<(EntityId, Blob, TilePosition)>()
-        .filter_map(|(id, _, tile_pos)| {
-            if triggered_tiles.iter().any(|t| *t == *tile_pos) {
-                Some(id.0)
+        .filter(|(_, blob, _)| blob.hold.is_none())
+        .filter_map(|(id, blob, tile_pos)| {
+            let tile = (tile_pos.0, tile_pos.1);
+            let reaches = |&(t, team, shape, _): &(TilePosition, PadTeam, HitShape, bool)| {
+                blob.teams.contains(team) && shape.covers(grid, (t.0, t.1), tile)
+            };
+            let chord = blob.teams.teams().into_iter().all(|team| {
+                triggers
+                    .iter()
+                    .any(|trigger| reaches(trigger) && trigger.1 == team)
+            });
+            if !chord {
+                return None;
+            }
+            triggers
+                .iter()
+                .position(|trigger| reaches(trigger) && trigger.3)
+                .map(|trigger| (blob.spawn_index, (trigger, *tile_pos), id.0))
+        })
+        .collect();
+    if grid.tile_capacity().is_some() {
+        hit.sort_by_key(|&(spawn_index, _, _)| spawn_index);
+        let mut used = Vec::new();
+        hit.retain(|&(_, trigger_tile, _)| {
+            if used.contains(&trigger_tile) {
+                false
             } else {
-                None
+                used.push(trigger_tile);
+                true
             }
+        });
+    }
+    let hit: Vec<usize> = hit.into_iter().map(|(_, _, id)| id).collect();
+
+    let mut remove = Vec::new();
+    for (id, mut blob) in world.with_components::<(EntityId, Blob)>() {
+        if !hit.contains(&id.0) {
+            continue;
+        }
+        if blob.length > 1 {
+            blob.hold = Some(0.0);
+        } else {
+            remove.push(id.0);
+        }
+    }
+    let credit = remove.len() as f32;
+    kill_blobs(remove, world);
+
+    Ok(credit)
+}
+
+/// Advances long blobs being held by `beats`, they die with full credit once they have been held
+/// for their length or with partial credit when their pads are let go early or moved off them.
+/// Returns the credit earned.
+pub fn hold_blobs(
+    beats: f32,
+    controls: &Controls,
+    grid: &Grid,
+    world: &mut World,
+) -> Result<f32, Error> {
+    let pads: Vec<(PadTeam, TilePosition, HitShape)> = world
+        .with_components::<(Pad, PadTeam, TilePosition)>()
+        .map(|(pad, team, tile_pos)| (*team, *tile_pos, pad.hit_shape))
+        .collect();
+
+    let mut credit = 0.0;
+    let mut remove = Vec::new();
+    for (id, mut blob, tile_pos) in world.with_components::<(EntityId, Blob, TilePosition)>() {
+        let held = match blob.hold {
+            Some(held) => held,
+            None => continue,
+        };
+        let length = blob.length as f32;
+        let tile = (tile_pos.0, tile_pos.1);
+        let down = blob.teams.teams().into_iter().all(|team| {
+            controls.is_down(Command::HitPad(team))
+                && pads.iter().any(|&(t, pad_pos, shape)| {
+                    t == team && shape.covers(grid, (pad_pos.0, pad_pos.1), tile)
+                })
+        });
+        if !down {
+            credit += held / length;
+            remove.push(id.0);
+        } else if held + beats >= length {
+            credit += 1.0;
+            remove.push(id.0);
+        } else {
+            blob.hold = Some(held + beats);
+        }
+    }
+    kill_blobs(remove, world);
+
+    Ok(credit)
+}
+
+fn kill_blobs(remove: Vec<usize>, world: &mut World) {
+    // blobs with a dying animation leave behind a copy of themselves that plays it
+    let dying: Vec<(usize, Position, Sprite, Animation)> = world
+        .with_components::<(EntityId, Position, Sprite, Animation)>()
+        .filter(|(id, _, _, animation)| {
+            remove.contains(&id.0) && animation.has_clip(AnimationState::Dying)
+        })
+        .map(|(id, position, sprite, animation)| {
+            let mut animation = animation.clone();
+            animation.set_state(AnimationState::Dying);
+            (id.0, Position(position.0), sprite.clone(), animation)
         })
         .collect();
+    for (id, position, sprite, animation) in dying {
+        // the copy keeps its team so it is still tinted like the blob
+        let team = world
+            .with_components::<(EntityId, PadTeam)>()
+            .find(|(e, _)| e.0 == id)
+            .map(|(_, team)| *team);
+        let entity = world
+            .add_entity()
+            .insert(position)
+            .insert(sprite)
+            .insert(animation)
+            .insert(RenderLayer::Effects);
+        if let Some(team) = team {
+            entity.insert(team);
+        }
+    }
+
     for id in remove {
         world.remove_entity(id);
     }
-
-    Ok(())
 }
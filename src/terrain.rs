@@ -0,0 +1,38 @@
+use movement::STEP_COST;
+
+// extra path cost of stepping into mud
+pub const MUD_COST: i32 = 2 * STEP_COST;
+// beats a blob is stuck after stepping into mud
+pub const MUD_HOLD: i32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn offset(&self) -> (i32, i32) {
+        match *self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+/// Ground type of a tile changing how blobs move across it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Terrain {
+    /// Expensive to path through and holds blobs for an extra beat.
+    Mud,
+    /// Blobs keep sliding in the direction they entered in until something stops them.
+    Ice,
+    /// Pushes blobs one tile per beat in a direction.
+    Conveyor(Direction),
+    /// Can only be left in its direction and never entered against it.
+    OneWay(Direction),
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use failure::{err_msg, Error};
+
+use components::{AnimationClip, AnimationState, AnimationTiming};
+
+static DEFAULT_THEME: &str = include_str!("../assets/theme.cfg");
+pub static THEME_PATH: &str = "assets/theme.cfg";
+
+// how often the theme file is checked for changes, in seconds
+const POLL_INTERVAL: f32 = 0.5;
+
+type Color = (f32, f32, f32, f32);
+
+/// Colors and sizes used to draw the game.
+#[derive(Clone)]
+pub struct Theme {
+    pub clear_color: Color,
+    pub hud_color: Color,
+    pub grid_color: Color,
+    pub grid_hover_color: Color,
+    pub grid_gap: f32,
+    pub wall_color: Color,
+    pub mud_color: Color,
+    pub ice_color: Color,
+    pub conveyor_color: Color,
+    pub one_way_color: Color,
+    pub arrow_color: Color,
+    pub teleporter_color: Color,
+    pub blob_radius: f32,
+    pub long_blob_radius: f32,
+    pub spawn_radius: f32,
+    pub spawn_outline: f32,
+    pub goal_radius: f32,
+    pub goal_outline: f32,
+    pub pad_radius: f32,
+    pub pad_pulse_radius: f32,
+    pub pad_outline: f32,
+    pub pad_selected_outline: f32,
+    pub hit_highlight_alpha: f32,
+    pub gate_radius: f32,
+    pub gate_outline: f32,
+    pub blob_sprite: Option<String>,
+    pub blob_sprite_frame: Option<(u32, u32)>,
+    pub blob_animations: Vec<(AnimationState, AnimationClip)>,
+    pub pad_sprite: Option<String>,
+    pub pad_sprite_frame: Option<(u32, u32)>,
+    pub pad_animations: Vec<(AnimationState, AnimationClip)>,
+    pub wall_sprite: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let mut theme = Theme::empty();
+        theme.apply(DEFAULT_THEME).unwrap();
+        theme
+    }
+}
+
+impl Theme {
+    /// A theme with nothing set, so every value comes from the theme source applied to it.
+    fn empty() -> Theme {
+        Theme {
+            clear_color: (0.0, 0.0, 0.0, 1.0),
+            hud_color: (0.0, 0.0, 0.0, 1.0),
+            grid_color: (0.0, 0.0, 0.0, 1.0),
+            grid_hover_color: (0.0, 0.0, 0.0, 1.0),
+            grid_gap: 0.0,
+            wall_color: (0.0, 0.0, 0.0, 1.0),
+            mud_color: (0.0, 0.0, 0.0, 1.0),
+            ice_color: (0.0, 0.0, 0.0, 1.0),
+            conveyor_color: (0.0, 0.0, 0.0, 1.0),
+            one_way_color: (0.0, 0.0, 0.0, 1.0),
+            arrow_color: (0.0, 0.0, 0.0, 1.0),
+            teleporter_color: (0.0, 0.0, 0.0, 1.0),
+            blob_radius: 0.0,
+            long_blob_radius: 0.0,
+            spawn_radius: 0.0,
+            spawn_outline: 0.0,
+            goal_radius: 0.0,
+            goal_outline: 0.0,
+            pad_radius: 0.0,
+            pad_pulse_radius: 0.0,
+            pad_outline: 0.0,
+            pad_selected_outline: 0.0,
+            hit_highlight_alpha: 0.0,
+            gate_radius: 0.0,
+            gate_outline: 0.0,
+            blob_sprite: None,
+            blob_sprite_frame: None,
+            blob_animations: Vec::new(),
+            pad_sprite: None,
+            pad_sprite_frame: None,
+            pad_animations: Vec::new(),
+            wall_sprite: None,
+        }
+    }
+
+    /// Loads the theme file, falling back to the built in theme if there is no file to read.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, Error> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(_) => return Ok(Theme::default()),
+        };
+        // the file replaces the built in theme rather than adding to it, so animation clips
+        // aren't listed twice and clips removed from the file are gone
+        let mut theme = Theme::empty();
+        theme.apply(&source)?;
+        Ok(theme)
+    }
+
+    /// Overrides the values set in a theme source, one `name = value` per line.
+    pub fn apply(&mut self, source: &str) -> Result<(), Error> {
+        for (i, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| err_msg(format!("line {}: expected `name = value`", i + 1)))?
+                .trim();
+
+            let numbers = || {
+                value
+                    .split_whitespace()
+                    .map(|v| v.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| err_msg(format!("line {}: {}", i + 1, e)))
+            };
+            let color = || match numbers()?[..] {
+                [r, g, b, a] => Ok((r, g, b, a)),
+                _ => Err(err_msg(format!("line {}: expected a color `r g b a`", i + 1))),
+            };
+            let number = || match numbers()?[..] {
+                [v] => Ok(v),
+                _ => Err(err_msg(format!("line {}: expected a number", i + 1))),
+            };
+            let frame_size = || match numbers()?[..] {
+                [w, h] if w >= 1.0 && h >= 1.0 => Ok(Some((w as u32, h as u32))),
+                [_, _] => Ok(None),
+                _ => Err(err_msg(format!("line {}: expected a frame size `w h`", i + 1))),
+            };
+            let animation = || {
+                parse_animation(value).map_err(|e| err_msg(format!("line {}: {}", i + 1, e)))
+            };
+            // an empty path means no sprite
+            let path = || {
+                if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_string())
+                }
+            };
+
+            match name {
+                "clear_color" => self.clear_color = color()?,
+                "hud_color" => self.hud_color = color()?,
+                "grid_color" => self.grid_color = color()?,
+                "grid_hover_color" => self.grid_hover_color = color()?,
+                "grid_gap" => self.grid_gap = number()?,
+                "wall_color" => self.wall_color = color()?,
+                "mud_color" => self.mud_color = color()?,
+                "ice_color" => self.ice_color = color()?,
+                "conveyor_color" => self.conveyor_color = color()?,
+                "one_way_color" => self.one_way_color = color()?,
+                "arrow_color" => self.arrow_color = color()?,
+                "teleporter_color" => self.teleporter_color = color()?,
+                "blob_radius" => self.blob_radius = number()?,
+                "long_blob_radius" => self.long_blob_radius = number()?,
+                "spawn_radius" => self.spawn_radius = number()?,
+                "spawn_outline" => self.spawn_outline = number()?,
+                "goal_radius" => self.goal_radius = number()?,
+                "goal_outline" => self.goal_outline = number()?,
+                "pad_radius" => self.pad_radius = number()?,
+                "pad_pulse_radius" => self.pad_pulse_radius = number()?,
+                "pad_outline" => self.pad_outline = number()?,
+                "pad_selected_outline" => self.pad_selected_outline = number()?,
+                "hit_highlight_alpha" => self.hit_highlight_alpha = number()?,
+                "gate_radius" => self.gate_radius = number()?,
+                "gate_outline" => self.gate_outline = number()?,
+                "blob_sprite" => self.blob_sprite = path(),
+                "blob_sprite_frame" => self.blob_sprite_frame = frame_size()?,
+                "blob_animation" => self.blob_animations.push(animation()?),
+                "pad_sprite" => self.pad_sprite = path(),
+                "pad_sprite_frame" => self.pad_sprite_frame = frame_size()?,
+                "pad_animation" => self.pad_animations.push(animation()?),
+                "wall_sprite" => self.wall_sprite = path(),
+                _ => return Err(err_msg(format!("line {}: unknown value `{}`", i + 1, name))),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `<state> [once] <fps|beats> <rate> <frames...>`.
+fn parse_animation(value: &str) -> Result<(AnimationState, AnimationClip), Error> {
+    let mut tokens = value.split_whitespace().peekable();
+
+    let state = match tokens.next() {
+        Some("idle") => AnimationState::Idle,
+        Some("moving") => AnimationState::Moving,
+        Some("hit") => AnimationState::Hit,
+        Some("dying") => AnimationState::Dying,
+        _ => return Err(err_msg("expected one of idle, moving, hit or dying")),
+    };
+    let looping = if tokens.peek() == Some(&"once") {
+        tokens.next();
+        false
+    } else {
+        true
+    };
+    let rate = |v: Option<&str>| -> Result<f32, Error> {
+        Ok(v.ok_or_else(|| err_msg("expected a rate"))?.parse::<f32>()?)
+    };
+    let timing = match tokens.next() {
+        Some("fps") => AnimationTiming::Fps(rate(tokens.next())?),
+        Some("beats") => AnimationTiming::Beats(rate(tokens.next())?),
+        _ => return Err(err_msg("expected fps or beats")),
+    };
+    let frames = tokens
+        .map(|t| t.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if frames.is_empty() {
+        return Err(err_msg("expected at least one frame"));
+    }
+
+    Ok((
+        state,
+        AnimationClip {
+            frames,
+            timing,
+            looping,
+        },
+    ))
+}
+
+/// Watches the theme file and reloads it when it changes on disk.
+pub struct ThemeWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    timer: f32,
+}
+
+impl ThemeWatcher {
+    pub fn new<P: Into<PathBuf>>(path: P) -> ThemeWatcher {
+        let path = path.into();
+        let modified = modified_time(&path);
+        ThemeWatcher {
+            path,
+            modified,
+            timer: POLL_INTERVAL,
+        }
+    }
+
+    /// Returns the reloaded theme if the file was changed since the last poll.
+    pub fn poll(&mut self, dt: f32) -> Option<Result<Theme, Error>> {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return None;
+        }
+        self.timer = POLL_INTERVAL;
+
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(Theme::load(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use components::{AnimationClip, AnimationState};
+    use theme::{Theme, THEME_PATH};
+
+    fn states(clips: &[(AnimationState, AnimationClip)]) -> Vec<AnimationState> {
+        clips.iter().map(|&(state, _)| state).collect()
+    }
+
+    #[test]
+    fn loading_the_theme_file_lists_each_clip_once() {
+        let theme = Theme::load(THEME_PATH).unwrap();
+        assert_eq!(
+            states(&theme.blob_animations),
+            vec![AnimationState::Idle, AnimationState::Moving]
+        );
+        assert_eq!(states(&theme.pad_animations), vec![AnimationState::Idle]);
+    }
+
+    #[test]
+    fn clips_removed_from_the_file_are_gone() {
+        let path = env::temp_dir().join("theme_without_clips.cfg");
+        fs::write(&path, "blob_radius = 12.0\n").unwrap();
+        let theme = Theme::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(theme.blob_radius, 12.0);
+        assert!(theme.blob_animations.is_empty());
+        assert!(theme.pad_animations.is_empty());
+    }
+}
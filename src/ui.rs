@@ -0,0 +1,634 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use embla::input::{Input, Key, MouseButton};
+use embla::math::Vec2;
+use failure::Error;
+
+use camera::Camera;
+use components::RenderLayer;
+use font::{text_width, GLYPH_HEIGHT};
+use render_interface::RenderInterface;
+
+type Color = (f32, f32, f32, f32);
+
+// number of arrow key presses that move a slider from one end to the other
+const SLIDER_STEPS: f32 = 10.0;
+
+static TEXT_KEYS: &[(Key, char)] = &[
+    (Key::A, 'A'),
+    (Key::B, 'B'),
+    (Key::C, 'C'),
+    (Key::D, 'D'),
+    (Key::E, 'E'),
+    (Key::F, 'F'),
+    (Key::G, 'G'),
+    (Key::H, 'H'),
+    (Key::I, 'I'),
+    (Key::J, 'J'),
+    (Key::K, 'K'),
+    (Key::L, 'L'),
+    (Key::M, 'M'),
+    (Key::N, 'N'),
+    (Key::O, 'O'),
+    (Key::P, 'P'),
+    (Key::Q, 'Q'),
+    (Key::R, 'R'),
+    (Key::S, 'S'),
+    (Key::T, 'T'),
+    (Key::U, 'U'),
+    (Key::V, 'V'),
+    (Key::W, 'W'),
+    (Key::X, 'X'),
+    (Key::Y, 'Y'),
+    (Key::Z, 'Z'),
+    (Key::Key0, '0'),
+    (Key::Key1, '1'),
+    (Key::Key2, '2'),
+    (Key::Key3, '3'),
+    (Key::Key4, '4'),
+    (Key::Key5, '5'),
+    (Key::Key6, '6'),
+    (Key::Key7, '7'),
+    (Key::Key8, '8'),
+    (Key::Key9, '9'),
+    (Key::Space, ' '),
+];
+
+/// Sizes in screen pixels and colors of the widgets.
+pub struct UiStyle {
+    /// Size of a font dot.
+    pub text_size: f32,
+    pub padding: f32,
+    pub spacing: f32,
+    /// Width of lists and text fields.
+    pub widget_width: f32,
+    /// Corner radius of panels and widget backgrounds.
+    pub corner_radius: f32,
+    pub text_color: Color,
+    pub panel_color: Color,
+    pub widget_color: Color,
+    pub hover_color: Color,
+    pub active_color: Color,
+    pub accent_color: Color,
+    pub focus_color: Color,
+}
+
+impl Default for UiStyle {
+    fn default() -> UiStyle {
+        UiStyle {
+            text_size: 2.0,
+            padding: 4.0,
+            spacing: 4.0,
+            widget_width: 160.0,
+            corner_radius: 4.0,
+            text_color: (1.0, 1.0, 1.0, 1.0),
+            panel_color: (0.0, 0.0, 0.0, 0.8),
+            widget_color: (0.25, 0.25, 0.25, 1.0),
+            hover_color: (0.35, 0.35, 0.35, 1.0),
+            active_color: (0.45, 0.45, 0.45, 1.0),
+            accent_color: (0.4, 0.6, 1.0, 1.0),
+            focus_color: (1.0, 1.0, 0.4, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Row,
+    Column,
+}
+
+struct Layout {
+    direction: Direction,
+    /// Top left corner of the next widget.
+    cursor: Vec2,
+    /// Bottom right corner of everything placed so far.
+    extent: Vec2,
+    /// Hashed into the ids of the widgets in the layout, so labels only have to be unique
+    /// within a row or column.
+    scope: u64,
+    // number of rows and columns nested in this one so far
+    children: u64,
+}
+
+enum UiDraw {
+    Rect((f32, f32, f32, f32), Color),
+    RoundedRect((f32, f32, f32, f32), Color),
+    Text(String, Vec2, Color),
+}
+
+type WidgetId = u64;
+
+/// Immediate-mode widgets drawn in world space on top of everything else.
+///
+/// Widgets are declared every frame between `begin` and `end`, each call handles the input for
+/// the widget and queues its drawing for `render`. Widgets are identified by their label and the
+/// row or column they are in, so labels have to be unique within a row or column.
+pub struct Ui {
+    pub style: UiStyle,
+    // size of a screen pixel in world units
+    pixel: f32,
+    origin: Vec2,
+    // the camera's viewport in world units, widgets are clipped to it
+    clip: (f32, f32, f32, f32),
+    mouse_position: Vec2,
+    mouse_down: bool,
+    mouse_pressed: bool,
+    typed: Vec<char>,
+    backspace: bool,
+    activate: bool,
+    step: i32,
+    hot: Option<WidgetId>,
+    active: Option<WidgetId>,
+    focus: Option<WidgetId>,
+    keyboard_focus: bool,
+    focusable: Vec<WidgetId>,
+    layouts: Vec<Layout>,
+    draws: Vec<UiDraw>,
+}
+
+impl Ui {
+    pub fn new(style: UiStyle) -> Ui {
+        Ui {
+            style,
+            pixel: 1.0,
+            origin: Vec2::zero(),
+            clip: (0.0, 0.0, 0.0, 0.0),
+            mouse_position: Vec2::zero(),
+            mouse_down: false,
+            mouse_pressed: false,
+            typed: Vec::new(),
+            backspace: false,
+            activate: false,
+            step: 0,
+            hot: None,
+            active: None,
+            focus: None,
+            keyboard_focus: false,
+            focusable: Vec::new(),
+            layouts: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Starts a frame of widgets laid out in a column from `origin`, the top left corner.
+    pub fn begin(&mut self, input: &Input, camera: &Camera, origin: Vec2) {
+        self.pixel = 1.0 / camera.scale();
+        self.clip = camera.world_viewport();
+        self.mouse_position = camera.mouse_to_world(input.mouse_position());
+        let mouse_down = input.mouse_button_is_down(&MouseButton::Left);
+        self.mouse_pressed = mouse_down && !self.mouse_down;
+        self.mouse_down = mouse_down;
+        if !mouse_down {
+            self.active = None;
+        }
+
+        self.typed = TEXT_KEYS
+            .iter()
+            .filter(|(key, _)| input.key_is_pressed(key))
+            .map(|&(_, c)| c)
+            .collect();
+        self.backspace = input.key_is_pressed(&Key::Backspace);
+        self.activate = input.key_is_pressed(&Key::Return);
+        self.step = if input.key_is_pressed(&Key::Left) || input.key_is_pressed(&Key::Up) {
+            -1
+        } else if input.key_is_pressed(&Key::Right) || input.key_is_pressed(&Key::Down) {
+            1
+        } else {
+            0
+        };
+
+        // tab moves focus through the widgets of the last frame
+        if input.key_is_pressed(&Key::Tab) && !self.focusable.is_empty() {
+            let next = match self.focus {
+                Some(id) => match self.focusable.iter().position(|&f| f == id) {
+                    Some(i) => (i + 1) % self.focusable.len(),
+                    None => 0,
+                },
+                None => 0,
+            };
+            self.focus = Some(self.focusable[next]);
+        }
+
+        self.hot = None;
+        self.keyboard_focus = false;
+        self.focusable.clear();
+        self.draws.clear();
+        self.origin = origin;
+        self.layouts = vec![Layout {
+            direction: Direction::Column,
+            cursor: origin,
+            extent: origin,
+            scope: 0,
+            children: 0,
+        }];
+    }
+
+    /// Finishes the frame, putting a panel behind all widgets.
+    pub fn end(&mut self) {
+        if self.mouse_pressed && self.hot.is_none() {
+            self.focus = None;
+        }
+        if self.draws.is_empty() {
+            return;
+        }
+
+        let padding = self.style.padding * self.pixel;
+        let extent = self.layouts[0].extent;
+        let panel = (
+            self.origin.0 - padding,
+            extent.1 - padding,
+            extent.0 + padding,
+            self.origin.1 + padding,
+        );
+        self.draws
+            .insert(0, UiDraw::RoundedRect(panel, self.style.panel_color));
+    }
+
+    /// True if the mouse is over a widget or dragging one, so the game should ignore it.
+    pub fn wants_mouse(&self) -> bool {
+        self.hot.is_some() || self.active.is_some()
+    }
+
+    /// True while a text field or list has focus, so the game should ignore the keyboard.
+    pub fn wants_keyboard(&self) -> bool {
+        self.keyboard_focus
+    }
+
+    /// Lays out the widgets added by `f` next to each other.
+    pub fn row<F: FnOnce(&mut Ui)>(&mut self, f: F) {
+        self.nested(Direction::Row, f);
+    }
+
+    /// Lays out the widgets added by `f` below each other.
+    pub fn column<F: FnOnce(&mut Ui)>(&mut self, f: F) {
+        self.nested(Direction::Column, f);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let size = self.text_size(text);
+        let rect = self.allocate(size);
+        self.text(text, rect);
+    }
+
+    /// Returns true when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.widget_id(label);
+        let size = self.text_size(label);
+        let rect = self.allocate(size);
+        let clicked = self.interact(id, rect);
+
+        let color = self.widget_color(id);
+        self.draws.push(UiDraw::RoundedRect(rect, color));
+        self.text(label, rect);
+        self.focus_outline(id, rect);
+
+        clicked
+    }
+
+    /// Returns true when `value` was toggled.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.widget_id(label);
+        let text_size = self.text_size(label);
+        let box_size = text_size.1;
+        let rect = self.allocate(Vec2::new(box_size + text_size.0, box_size));
+        let clicked = self.interact(id, rect);
+        if clicked {
+            *value = !*value;
+        }
+
+        let check = (rect.0, rect.1, rect.0 + box_size, rect.3);
+        let color = self.widget_color(id);
+        self.draws.push(UiDraw::RoundedRect(check, color));
+        if *value {
+            let inset = self.style.padding * self.pixel;
+            let mark = (
+                check.0 + inset,
+                check.1 + inset,
+                check.2 - inset,
+                check.3 - inset,
+            );
+            self.draws.push(UiDraw::Rect(mark, self.style.accent_color));
+        }
+        self.text(label, (check.2, rect.1, rect.2, rect.3));
+        self.focus_outline(id, rect);
+
+        clicked
+    }
+
+    /// A bar filled up to `value` between `min` and `max`, dragged with the mouse or stepped
+    /// with the arrow keys. Returns true when `value` changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.widget_id(label);
+        let height = self.text_size(label).1;
+        let width = self.style.widget_width * self.pixel;
+        let rect = self.allocate(Vec2::new(width, height));
+        self.interact(id, rect);
+
+        let old = *value;
+        if self.focus == Some(id) {
+            self.keyboard_focus = true;
+        }
+        if self.active == Some(id) {
+            let t = (self.mouse_position.0 - rect.0) / (rect.2 - rect.0);
+            *value = min + t.clamp(0.0, 1.0) * (max - min);
+        } else if self.focus == Some(id) && self.step != 0 {
+            let step = (max - min) / SLIDER_STEPS;
+            *value = (*value + step * self.step as f32).clamp(min, max);
+        }
+
+        let color = self.widget_color(id);
+        self.draws.push(UiDraw::RoundedRect(rect, color));
+        let t = if max > min {
+            ((*value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if t > 0.0 {
+            let track = (rect.0, rect.1, rect.0 + (rect.2 - rect.0) * t, rect.3);
+            self.draws
+                .push(UiDraw::RoundedRect(track, self.style.accent_color));
+        }
+        self.text(label, rect);
+        self.focus_outline(id, rect);
+
+        *value != old
+    }
+
+    /// Shows `items` below each other, returns true when `selected` changed.
+    pub fn list(&mut self, label: &str, items: &[&str], selected: &mut usize) -> bool {
+        let id = self.widget_id(label);
+        let line_height = self.text_size(label).1;
+        let width = self.style.widget_width * self.pixel;
+        let rect = self.allocate(Vec2::new(width, line_height * items.len() as f32));
+        let clicked = self.interact(id, rect);
+
+        let old = *selected;
+        if self.focus == Some(id) {
+            self.keyboard_focus = true;
+        }
+        if clicked && !items.is_empty() {
+            let row = ((rect.3 - self.mouse_position.1) / line_height) as usize;
+            *selected = row.min(items.len() - 1);
+        } else if self.focus == Some(id) && self.step != 0 && !items.is_empty() {
+            let row = *selected as i32 + self.step;
+            *selected = row.max(0).min(items.len() as i32 - 1) as usize;
+        }
+
+        self.draws
+            .push(UiDraw::RoundedRect(rect, self.style.widget_color));
+        for (i, item) in items.iter().enumerate() {
+            let top = rect.3 - line_height * i as f32;
+            let row = (rect.0, top - line_height, rect.2, top);
+            if i == *selected {
+                self.draws.push(UiDraw::Rect(row, self.style.active_color));
+            }
+            self.text(item, row);
+        }
+        self.focus_outline(id, rect);
+
+        *selected != old
+    }
+
+    /// Single line text input, returns true when `text` changed.
+    pub fn text_field(&mut self, label: &str, text: &mut String) -> bool {
+        let id = self.widget_id(label);
+        let height = self.text_size(label).1;
+        let width = self.style.widget_width * self.pixel;
+        let rect = self.allocate(Vec2::new(width, height));
+        self.interact(id, rect);
+
+        let old = text.clone();
+        if self.focus == Some(id) {
+            self.keyboard_focus = true;
+            text.extend(self.typed.iter());
+            if self.backspace {
+                text.pop();
+            }
+        }
+
+        let color = self.widget_color(id);
+        self.draws.push(UiDraw::RoundedRect(rect, color));
+        if text.is_empty() && self.focus != Some(id) {
+            let hint = self.style.hover_color;
+            self.text_colored(label, rect, hint);
+        } else if self.focus == Some(id) {
+            self.text(&format!("{}_", text), rect);
+        } else {
+            self.text(text, rect);
+        }
+        self.focus_outline(id, rect);
+
+        *text != old
+    }
+
+    pub fn render(&self, renderer: &mut RenderInterface) -> Result<(), Error> {
+        renderer.set_offset(Vec2::zero());
+        renderer.set_layer(RenderLayer::Hud);
+        renderer.set_clip(Some(self.clip));
+        let size = self.style.text_size * self.pixel;
+        let radius = self.style.corner_radius * self.pixel;
+        for draw in &self.draws {
+            match *draw {
+                UiDraw::Rect(rect, color) => renderer.draw_rect(rect, color)?,
+                UiDraw::RoundedRect(rect, color) => {
+                    renderer.draw_rounded_rect(rect, radius, color)?
+                }
+                UiDraw::Text(ref text, position, color) => {
+                    renderer.draw_text(text, position, size, color)?
+                }
+            }
+        }
+        renderer.set_clip(None);
+
+        Ok(())
+    }
+
+    fn nested<F: FnOnce(&mut Ui)>(&mut self, direction: Direction, f: F) {
+        let (cursor, scope) = {
+            let parent = self.layouts.last_mut().unwrap();
+            parent.children += 1;
+            (parent.cursor, hash(&(parent.scope, parent.children)))
+        };
+        self.layouts.push(Layout {
+            direction,
+            cursor,
+            extent: cursor,
+            scope,
+            children: 0,
+        });
+        f(self);
+
+        let layout = self.layouts.pop().unwrap();
+        let size = Vec2::new(
+            layout.extent.0 - cursor.0,
+            cursor.1 - layout.extent.1,
+        );
+        self.allocate(size);
+    }
+
+    /// Reserves space for a widget in the current layout, returns its rect.
+    fn allocate(&mut self, size: Vec2) -> (f32, f32, f32, f32) {
+        let spacing = self.style.spacing * self.pixel;
+        let layout = self.layouts.last_mut().unwrap();
+        let min = layout.cursor;
+        let rect = (min.0, min.1 - size.1, min.0 + size.0, min.1);
+
+        layout.extent = Vec2::new(layout.extent.0.max(rect.2), layout.extent.1.min(rect.1));
+        layout.cursor = match layout.direction {
+            Direction::Row => Vec2::new(rect.2 + spacing, min.1),
+            Direction::Column => Vec2::new(min.0, rect.1 - spacing),
+        };
+
+        rect
+    }
+
+    /// Updates hover, drag and focus state of a widget, returns true when it was clicked or
+    /// activated from the keyboard.
+    fn interact(&mut self, id: WidgetId, rect: (f32, f32, f32, f32)) -> bool {
+        self.focusable.push(id);
+
+        let p = self.mouse_position;
+        let hovered = p.0 >= rect.0 && p.0 < rect.2 && p.1 >= rect.1 && p.1 < rect.3;
+        if hovered {
+            self.hot = Some(id);
+        }
+        if hovered && self.mouse_pressed {
+            self.active = Some(id);
+            self.focus = Some(id);
+            return true;
+        }
+
+        self.focus == Some(id) && self.activate
+    }
+
+    fn widget_id(&self, label: &str) -> WidgetId {
+        hash(&(self.layouts.last().unwrap().scope, label))
+    }
+
+    fn widget_color(&self, id: WidgetId) -> Color {
+        if self.active == Some(id) {
+            self.style.active_color
+        } else if self.hot == Some(id) {
+            self.style.hover_color
+        } else {
+            self.style.widget_color
+        }
+    }
+
+    /// Size of a widget showing a line of text.
+    fn text_size(&self, text: &str) -> Vec2 {
+        let size = self.style.text_size * self.pixel;
+        let padding = self.style.padding * self.pixel;
+        Vec2::new(
+            text_width(text, size) + padding * 2.0,
+            GLYPH_HEIGHT as f32 * size + padding * 2.0,
+        )
+    }
+
+    fn text(&mut self, text: &str, rect: (f32, f32, f32, f32)) {
+        let color = self.style.text_color;
+        self.text_colored(text, rect, color);
+    }
+
+    fn text_colored(&mut self, text: &str, rect: (f32, f32, f32, f32), color: Color) {
+        let padding = self.style.padding * self.pixel;
+        let position = Vec2::new(rect.0 + padding, rect.1 + padding);
+        self.draws
+            .push(UiDraw::Text(text.to_string(), position, color));
+    }
+
+    fn focus_outline(&mut self, id: WidgetId, rect: (f32, f32, f32, f32)) {
+        if self.focus != Some(id) {
+            return;
+        }
+
+        let w = self.pixel;
+        let color = self.style.focus_color;
+        let sides = [
+            (rect.0, rect.1, rect.2, rect.1 + w),
+            (rect.0, rect.3 - w, rect.2, rect.3),
+            (rect.0, rect.1, rect.0 + w, rect.3),
+            (rect.2 - w, rect.1, rect.2, rect.3),
+        ];
+        for &side in &sides {
+            self.draws.push(UiDraw::Rect(side, color));
+        }
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use embla::math::Vec2;
+
+    use ui::{Direction, Layout, Ui, UiStyle, WidgetId};
+
+    fn start(ui: &mut Ui) {
+        ui.layouts = vec![Layout {
+            direction: Direction::Column,
+            cursor: Vec2::zero(),
+            extent: Vec2::zero(),
+            scope: 0,
+            children: 0,
+        }];
+    }
+
+    // ids of a "-" widget in each of two rows and in a column nested in the second row
+    fn ids(ui: &mut Ui) -> Vec<WidgetId> {
+        start(ui);
+        let mut ids = Vec::new();
+        ui.row(|ui| ids.push(ui.widget_id("-")));
+        ui.row(|ui| {
+            ids.push(ui.widget_id("-"));
+            ui.column(|ui| ids.push(ui.widget_id("-")));
+        });
+        ids
+    }
+
+    #[test]
+    fn same_labels_in_different_rows_are_different_widgets() {
+        let mut ui = Ui::new(UiStyle::default());
+        let first = ids(&mut ui);
+        assert_ne!(first[0], first[1]);
+        assert_ne!(first[1], first[2]);
+
+        // and keep their ids from frame to frame
+        assert_eq!(ids(&mut ui), first);
+    }
+
+    #[test]
+    fn sliders_step_with_the_arrow_keys_and_follow_a_drag() {
+        let mut ui = Ui::new(UiStyle::default());
+        let mut value = 0.5;
+        start(&mut ui);
+        ui.slider("ZOOM", &mut value, 0.0, 1.0);
+        let id = ui.focusable[0];
+
+        ui.focus = Some(id);
+        ui.step = 1;
+        start(&mut ui);
+        assert!(ui.slider("ZOOM", &mut value, 0.0, 1.0));
+        assert!((value - 0.6).abs() < 1e-5);
+
+        // dragging past the end clamps to it
+        ui.step = 0;
+        ui.active = Some(id);
+        ui.mouse_position = Vec2::new(-100.0, -5.0);
+        start(&mut ui);
+        assert!(ui.slider("ZOOM", &mut value, 0.0, 1.0));
+        assert_eq!(value, 0.0);
+
+        // halfway along the track
+        ui.mouse_position = Vec2::new(ui.style.widget_width / 2.0, -5.0);
+        start(&mut ui);
+        ui.slider("ZOOM", &mut value, 0.0, 1.0);
+        assert!((value - 0.5).abs() < 1e-5);
+    }
+}